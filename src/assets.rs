@@ -0,0 +1,52 @@
+use std::path::Path;
+use crate::campaign::Campaign;
+use crate::director::{SCARE_IMAGES, SCARE_SOUNDS};
+use crate::level::LevelConfig;
+
+// Texturas de las paredes
+pub const WALL_TEXTURE: &str = "patterned_plaster_wall_diff_4k.jpg";
+pub const CORNER_TEXTURE: &str = "patterned_plaster_wall_disp_4k.png";
+// Lado máximo al cargarlas: una celda ocupa pocos píxeles en pantalla y una
+// textura de 4k solo gasta memoria
+pub const WALL_TEXTURE_SIZE: u32 = 512;
+// Los sustos se ven a pantalla completa
+pub const SCARE_TEXTURE_SIZE: u32 = 1024;
+
+// Archivos que el juego espera encontrar. Sin ellos sigue andando, con
+// texturas de reemplazo o sin esos sonidos.
+const REQUIRED_FILES: [&str; 6] = [WALL_TEXTURE, CORNER_TEXTURE, SCARE_IMAGES[0], SCARE_SOUNDS[0].1, "music.mp3", "pasos.mp3"];
+
+// Archivos que faltan, sin repetir: los del juego, los laberintos de la
+// campaña y lo que pide la configuración de cada nivel
+pub fn missing_assets(campaign: &Campaign) -> Vec<String> {
+    let mut files: Vec<String> = REQUIRED_FILES.iter().map(|file| file.to_string()).collect();
+    for level in &campaign.levels {
+        files.push(level.file.clone());
+        let config = LevelConfig::load(&level.file);
+        files.push(config.music);
+        files.extend(config.emitters.into_iter().chain(config.triggers).map(|spot| spot.file));
+        files.extend(config.color_grade);
+    }
+
+    let mut missing = Vec::new();
+    for file in files {
+        if !Path::new(&file).exists() && !missing.contains(&file) {
+            missing.push(file);
+        }
+    }
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_missing_levels() {
+        let extra = ["maze1.txt".to_string(), "no_existe.txt".to_string()];
+        let missing = missing_assets(&Campaign::parse("", &extra));
+        assert!(missing.contains(&"no_existe.txt".to_string()));
+        assert!(!missing.contains(&"maze1.txt".to_string()));
+        assert!(!missing.contains(&"music.mp3".to_string()));
+    }
+}
@@ -0,0 +1,477 @@
+use rodio::source::Buffered;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::time::Duration;
+use nalgebra_glm::Vec2;
+use crate::director::{SCARE_SOUNDS, SCARE_VARIANTS};
+use crate::game::GameEvent;
+use crate::positional::{PositionalParams, PositionalSource, Spatial};
+
+type Sound = Buffered<Box<dyn Source<Item = i16> + Send>>;
+
+// Canales de mezcla; cada uno tiene su propio volumen
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Channel {
+    Music,
+    Sfx,
+    Ambience,
+    Ui,
+}
+
+const CHANNELS: [Channel; 4] = [Channel::Music, Channel::Sfx, Channel::Ambience, Channel::Ui];
+
+// Identifica una pista de música que está sonando
+pub type TrackId = usize;
+
+// Todo lo que el juego le pide al audio. Hay una implementación con rodio, una
+// muda para máquinas sin dispositivo de sonido y una que anota lo que se tocó.
+pub trait AudioBackend {
+    // Registra un sonido con un nombre
+    fn load(&mut self, sound_id: &str, file_path: &str, channel: Channel);
+
+    // Registra solo un fragmento de un archivo, por ejemplo un paso dentro de
+    // una grabación con varios pasos seguidos
+    fn load_slice(&mut self, sound_id: &str, file_path: &str, channel: Channel, start: Duration, length: Duration);
+
+    fn has_sound(&self, sound_id: &str) -> bool;
+
+    // Reproduce un sonido una vez sin bloquear, con volumen y tono propios
+    fn play_with(&mut self, sound_id: &str, volume: f32, pitch: f32);
+
+    fn play(&mut self, sound_id: &str) {
+        self.play_with(sound_id, 1.0, 1.0);
+    }
+
+    // Arranca una pista de música en bucle, en silencio. Su volumen lo maneja
+    // el MusicController con set_track_volume.
+    fn start_track(&mut self, sound_id: &str) -> Option<TrackId>;
+
+    fn set_track_volume(&mut self, track: TrackId, volume: f32);
+
+    fn stop_track(&mut self, track: TrackId);
+
+    // Reproduce un sonido ubicado en el mundo. Su volumen, paneo y filtro se
+    // recalculan cada cuadro en update_listener.
+    fn play_at(&mut self, sound_id: &str, pos: Vec2, looped: bool);
+
+    // Actualiza los sonidos del mundo para la posición actual del jugador
+    fn update_listener(&mut self, spatialize: &dyn Fn(Vec2) -> Spatial);
+
+    // Corta los sonidos del mundo y los efectos, al salir de un nivel
+    fn stop_world_sounds(&mut self);
+
+    fn set_channel_volume(&mut self, channel: Channel, volume: f32);
+
+    fn set_muted(&mut self, muted: bool);
+
+    // Pausa o reanuda los efectos y sonidos del mundo. La música sigue sonando;
+    // el MusicController la baja durante la pausa.
+    fn set_paused(&mut self, paused: bool);
+}
+
+// Abre el dispositivo de audio. Sin dispositivo, o si se pide explícitamente,
+// el juego sigue funcionando en silencio.
+pub fn open(disabled: bool) -> Box<dyn AudioBackend> {
+    if disabled {
+        return Box::new(NullBackend);
+    }
+    match RodioBackend::new() {
+        Ok(backend) => Box::new(backend),
+        Err(e) => {
+            eprintln!("No hay dispositivo de audio ({}); el juego seguirá sin sonido", e);
+            Box::new(NullBackend)
+        }
+    }
+}
+
+// Convierte un suceso de la partida en el sonido que le corresponde
+pub fn play_game_event(audio: &mut dyn AudioBackend, event: &GameEvent) {
+    match event {
+        GameEvent::Footstep(step) => {
+            let sound_id = step.sound_id();
+            if audio.has_sound(&sound_id) {
+                audio.play_with(&sound_id, step.volume, step.pitch);
+            } else {
+                let (volume, pitch) = step.fallback_tone();
+                audio.play_with(&step.fallback_sound_id(), volume, pitch);
+            }
+        }
+        GameEvent::SoundAt(sound_id, pos) => audio.play_at(sound_id, *pos, false),
+        // Sin sonido propio para los objetos se usa un paso agudo
+        GameEvent::Collected(_) if audio.has_sound("recoger") => audio.play("recoger"),
+        GameEvent::Collected(_) => audio.play_with("pasos_der", 1.0, 2.0),
+        GameEvent::Jumpscare(variant) => {
+            // El primer grito siempre está; los demás solo si se cargaron
+            let sounds: Vec<&str> = SCARE_SOUNDS
+                .iter()
+                .enumerate()
+                .filter(|(index, (sound_id, _))| *index == 0 || audio.has_sound(sound_id))
+                .map(|(_, (sound_id, _))| *sound_id)
+                .collect();
+            let pitch = SCARE_VARIANTS[*variant as usize % SCARE_VARIANTS.len()].pitch;
+            audio.play_with(sounds[*variant as usize % sounds.len()], 1.0, pitch);
+        }
+        GameEvent::Died | GameEvent::ReachedGoal => {}
+    }
+}
+
+struct ChannelState {
+    volume: f32,
+    // Sonidos que están sonando en el canal, junto con su nombre
+    playing: Vec<(String, Sink)>,
+}
+
+// Sonido con posición en el laberinto
+struct Emitter {
+    pos: Vec2,
+    channel: Channel,
+    params: Arc<PositionalParams>,
+    sink: Sink,
+}
+
+// Dueño único del dispositivo de audio. Los sonidos se decodifican una sola vez
+// al cargarlos y cada reproducción usa una copia del búfer ya decodificado.
+pub struct RodioBackend {
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    sounds: HashMap<String, (Sound, Channel)>,
+    channels: HashMap<Channel, ChannelState>,
+    emitters: Vec<Emitter>,
+    tracks: HashMap<TrackId, (Sink, f32)>,
+    next_track: TrackId,
+    muted: bool,
+    paused: bool,
+}
+
+impl RodioBackend {
+    pub fn new() -> Result<RodioBackend, rodio::StreamError> {
+        let (stream, handle) = OutputStream::try_default()?;
+        let channels = CHANNELS
+            .iter()
+            .map(|channel| (*channel, ChannelState { volume: 1.0, playing: Vec::new() }))
+            .collect();
+        Ok(RodioBackend {
+            _stream: stream,
+            handle,
+            sounds: HashMap::new(),
+            channels,
+            emitters: Vec::new(),
+            tracks: HashMap::new(),
+            next_track: 0,
+            muted: false,
+            paused: false,
+        })
+    }
+
+    fn open_decoder(file_path: &str) -> Option<Decoder<BufReader<File>>> {
+        let file = match File::open(file_path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("No se pudo abrir el sonido {}: {}", file_path, e);
+                return None;
+            }
+        };
+        match Decoder::new(BufReader::new(file)) {
+            Ok(decoder) => Some(decoder),
+            Err(e) => {
+                eprintln!("No se pudo decodificar el sonido {}: {}", file_path, e);
+                None
+            }
+        }
+    }
+
+    // La música se decodifica mientras suena la primera vez; el resto se
+    // decodifica completo aquí
+    fn insert(&mut self, sound_id: &str, source: Box<dyn Source<Item = i16> + Send>, channel: Channel) {
+        let sound = source.buffered();
+        if channel != Channel::Music {
+            sound.clone().for_each(drop);
+        }
+        self.sounds.insert(sound_id.to_string(), (sound, channel));
+    }
+
+    fn effective_volume(&self, channel: Channel) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.channels[&channel].volume
+        }
+    }
+
+    fn apply_volumes(&self) {
+        for (channel, state) in &self.channels {
+            let volume = self.effective_volume(*channel);
+            for (_, sink) in &state.playing {
+                sink.set_volume(volume);
+            }
+        }
+        for emitter in &self.emitters {
+            emitter.sink.set_volume(self.effective_volume(emitter.channel));
+        }
+        for (sink, volume) in self.tracks.values() {
+            sink.set_volume(self.effective_volume(Channel::Music) * volume);
+        }
+    }
+
+    fn new_sink(&self, channel: Channel) -> Option<Sink> {
+        let sink = Sink::try_new(&self.handle).ok()?;
+        sink.set_volume(self.effective_volume(channel));
+        if self.paused {
+            sink.pause();
+        }
+        Some(sink)
+    }
+
+    // Guarda el sink para poder cambiarle el volumen y libera los que ya terminaron
+    fn push_sink(&mut self, channel: Channel, sound_id: &str, sink: Sink) {
+        let state = self.channels.get_mut(&channel).unwrap();
+        state.playing.retain(|(_, sink)| !sink.empty());
+        state.playing.push((sound_id.to_string(), sink));
+    }
+}
+
+impl AudioBackend for RodioBackend {
+    fn load(&mut self, sound_id: &str, file_path: &str, channel: Channel) {
+        if let Some(decoder) = RodioBackend::open_decoder(file_path) {
+            self.insert(sound_id, Box::new(decoder), channel);
+        }
+    }
+
+    fn load_slice(&mut self, sound_id: &str, file_path: &str, channel: Channel, start: Duration, length: Duration) {
+        if let Some(decoder) = RodioBackend::open_decoder(file_path) {
+            self.insert(sound_id, Box::new(decoder.skip_duration(start).take_duration(length)), channel);
+        }
+    }
+
+    fn has_sound(&self, sound_id: &str) -> bool {
+        self.sounds.contains_key(sound_id)
+    }
+
+    fn play_with(&mut self, sound_id: &str, volume: f32, pitch: f32) {
+        let Some((sound, channel)) = self.sounds.get(sound_id) else { return };
+        let (source, channel) = (sound.clone(), *channel);
+        if let Some(sink) = self.new_sink(channel) {
+            sink.append(source.amplify(volume).speed(pitch));
+            self.push_sink(channel, sound_id, sink);
+        }
+    }
+
+    fn start_track(&mut self, sound_id: &str) -> Option<TrackId> {
+        let (sound, _) = self.sounds.get(sound_id)?;
+        let source = sound.clone();
+        let sink = Sink::try_new(&self.handle).ok()?;
+        sink.set_volume(0.0);
+        sink.append(source.repeat_infinite());
+
+        let track = self.next_track;
+        self.next_track += 1;
+        self.tracks.insert(track, (sink, 0.0));
+        Some(track)
+    }
+
+    fn set_track_volume(&mut self, track: TrackId, volume: f32) {
+        let channel_volume = self.effective_volume(Channel::Music);
+        if let Some((sink, track_volume)) = self.tracks.get_mut(&track) {
+            *track_volume = volume;
+            sink.set_volume(channel_volume * volume);
+        }
+    }
+
+    fn stop_track(&mut self, track: TrackId) {
+        self.tracks.remove(&track);
+    }
+
+    fn play_at(&mut self, sound_id: &str, pos: Vec2, looped: bool) {
+        let Some((sound, channel)) = self.sounds.get(sound_id) else { return };
+        let (source, channel) = (sound.clone().convert_samples::<f32>(), *channel);
+        let Some(sink) = self.new_sink(channel) else { return };
+        let params = PositionalParams::new();
+        if looped {
+            sink.append(PositionalSource::new(source.repeat_infinite(), Arc::clone(&params)));
+        } else {
+            sink.append(PositionalSource::new(source, Arc::clone(&params)));
+        }
+        self.emitters.push(Emitter { pos, channel, params, sink });
+    }
+
+    fn update_listener(&mut self, spatialize: &dyn Fn(Vec2) -> Spatial) {
+        self.emitters.retain(|emitter| !emitter.sink.empty());
+        for emitter in &self.emitters {
+            emitter.params.set(spatialize(emitter.pos));
+        }
+    }
+
+    fn stop_world_sounds(&mut self) {
+        self.emitters.clear();
+        for (channel, state) in &mut self.channels {
+            if *channel != Channel::Music {
+                state.playing.clear();
+            }
+        }
+    }
+
+    fn set_channel_volume(&mut self, channel: Channel, volume: f32) {
+        if let Some(state) = self.channels.get_mut(&channel) {
+            state.volume = volume;
+        }
+        self.apply_volumes();
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.apply_volumes();
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        let emitter_sinks = self.emitters.iter().map(|emitter| &emitter.sink);
+        let channel_sinks = self.channels
+            .iter()
+            .filter(|(channel, _)| **channel != Channel::Music)
+            .flat_map(|(_, state)| state.playing.iter().map(|(_, sink)| sink));
+        for sink in channel_sinks.chain(emitter_sinks) {
+            if paused {
+                sink.pause();
+            } else {
+                sink.play();
+            }
+        }
+    }
+}
+
+// Audio mudo: acepta todo y no hace nada
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+    fn load(&mut self, _sound_id: &str, _file_path: &str, _channel: Channel) {}
+
+    fn load_slice(&mut self, _sound_id: &str, _file_path: &str, _channel: Channel, _start: Duration, _length: Duration) {}
+
+    fn has_sound(&self, _sound_id: &str) -> bool {
+        false
+    }
+
+    fn play_with(&mut self, _sound_id: &str, _volume: f32, _pitch: f32) {}
+
+    fn start_track(&mut self, _sound_id: &str) -> Option<TrackId> {
+        None
+    }
+
+    fn set_track_volume(&mut self, _track: TrackId, _volume: f32) {}
+
+    fn stop_track(&mut self, _track: TrackId) {}
+
+    fn play_at(&mut self, _sound_id: &str, _pos: Vec2, _looped: bool) {}
+
+    fn update_listener(&mut self, _spatialize: &dyn Fn(Vec2) -> Spatial) {}
+
+    fn stop_world_sounds(&mut self) {}
+
+    fn set_channel_volume(&mut self, _channel: Channel, _volume: f32) {}
+
+    fn set_muted(&mut self, _muted: bool) {}
+
+    fn set_paused(&mut self, _paused: bool) {}
+}
+
+// Audio mudo que anota cada sonido que se pidió tocar, para las pruebas
+#[cfg(test)]
+pub struct RecordingBackend {
+    loaded: Vec<String>,
+    pub played: Vec<String>,
+    // Pistas de música por TrackId: nombre, volumen y si ya se detuvo
+    pub tracks: Vec<(String, f32, bool)>,
+}
+
+#[cfg(test)]
+impl RecordingBackend {
+    pub fn new() -> Self {
+        RecordingBackend { loaded: Vec::new(), played: Vec::new(), tracks: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+impl AudioBackend for RecordingBackend {
+    fn load(&mut self, sound_id: &str, _file_path: &str, _channel: Channel) {
+        self.loaded.push(sound_id.to_string());
+    }
+
+    fn load_slice(&mut self, sound_id: &str, _file_path: &str, _channel: Channel, _start: Duration, _length: Duration) {
+        self.loaded.push(sound_id.to_string());
+    }
+
+    fn has_sound(&self, sound_id: &str) -> bool {
+        self.loaded.iter().any(|id| id == sound_id)
+    }
+
+    fn play_with(&mut self, sound_id: &str, _volume: f32, _pitch: f32) {
+        self.played.push(sound_id.to_string());
+    }
+
+    fn start_track(&mut self, sound_id: &str) -> Option<TrackId> {
+        self.tracks.push((sound_id.to_string(), 0.0, false));
+        Some(self.tracks.len() - 1)
+    }
+
+    fn set_track_volume(&mut self, track: TrackId, volume: f32) {
+        self.tracks[track].1 = volume;
+    }
+
+    fn stop_track(&mut self, track: TrackId) {
+        self.tracks[track].2 = true;
+    }
+
+    fn play_at(&mut self, sound_id: &str, _pos: Vec2, _looped: bool) {
+        self.played.push(sound_id.to_string());
+    }
+
+    fn update_listener(&mut self, _spatialize: &dyn Fn(Vec2) -> Spatial) {}
+
+    fn stop_world_sounds(&mut self) {}
+
+    fn set_channel_volume(&mut self, _channel: Channel, _volume: f32) {}
+
+    fn set_muted(&mut self, _muted: bool) {}
+
+    fn set_paused(&mut self, _paused: bool) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, TICK_RATE};
+    use crate::input::InputState;
+    use crate::replay::FrameInput;
+
+    #[test]
+    fn test_walking_and_scares_trigger_sounds() {
+        let mut audio = RecordingBackend::new();
+        audio.load_slice("pasos_izq", "pasos.mp3", Channel::Sfx, Duration::ZERO, Duration::ZERO);
+        audio.load_slice("pasos_der", "pasos.mp3", Channel::Sfx, Duration::ZERO, Duration::ZERO);
+
+        let mut game = Game::new("maze1.txt", 99);
+        let mut input = InputState::new();
+        // Caminar hacia adelante y luego esperar a que la tensión traiga un susto
+        for tick in 0..40 * TICK_RATE {
+            let keys = if tick < 30 { 1 } else { 0 };
+            FrameInput { keys, mouse_x: None }.apply(&mut input);
+            for event in game.update(&input) {
+                play_game_event(&mut audio, &event);
+            }
+        }
+
+        assert!(audio.played.iter().any(|id| id == "pasos_izq"));
+        assert!(audio.played.iter().any(|id| id == "pasos_der"));
+        assert!(audio.played.iter().any(|id| id == "grito"));
+    }
+
+    #[test]
+    fn test_null_backend_has_no_sounds() {
+        let mut audio = NullBackend;
+        audio.load("musica", "music.mp3", Channel::Music);
+        assert!(!audio.has_sound("musica"));
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Archivo donde se guarda qué niveles ya se completaron
+pub const PROGRESS_FILE: &str = "progreso.sav";
+
+// Un nivel de la campaña
+pub struct CampaignLevel {
+    pub file: String,
+    pub name: String,
+}
+
+// Niveles en el orden en que se juegan. Se leen de campaign.txt, con líneas como:
+//
+//   maze1.txt = El sótano
+//   maze2.txt = Los pasillos
+//
+// Los laberintos maze*.txt que no estén en la lista, por ejemplo los generados,
+// se agregan al final en orden alfabético.
+pub struct Campaign {
+    pub levels: Vec<CampaignLevel>,
+}
+
+impl Campaign {
+    pub fn load(campaign_file: &str) -> Campaign {
+        let text = fs::read_to_string(campaign_file).unwrap_or_default();
+        let mut extra_files: Vec<String> = fs::read_dir(".")
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                    .filter(|name| name.starts_with("maze") && name.ends_with(".txt"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        extra_files.sort();
+        Campaign::parse(&text, &extra_files)
+    }
+
+    pub fn parse(text: &str, extra_files: &[String]) -> Campaign {
+        let mut levels = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (file, name) = match line.split_once('=') {
+                Some((file, name)) => (file.trim(), name.trim()),
+                None => (line, ""),
+            };
+            if !Path::new(file).exists() && !extra_files.iter().any(|extra| extra == file) {
+                eprintln!("Nivel {} de la campaña no encontrado en la línea {}", file, number + 1);
+                continue;
+            }
+            levels.push(CampaignLevel { file: file.to_string(), name: level_name(file, name) });
+        }
+
+        for file in extra_files {
+            if !levels.iter().any(|level| &level.file == file) {
+                levels.push(CampaignLevel { file: file.clone(), name: level_name(file, "") });
+            }
+        }
+
+        Campaign { levels }
+    }
+
+    pub fn index_of(&self, file: &str) -> Option<usize> {
+        self.levels.iter().position(|level| level.file == file)
+    }
+
+    // Nivel que sigue a `file`, o None si era el último
+    pub fn next_after(&self, file: &str) -> Option<&CampaignLevel> {
+        self.levels.get(self.index_of(file)? + 1)
+    }
+}
+
+// Sin nombre en la campaña se muestra el archivo sin extensión
+fn level_name(file: &str, name: &str) -> String {
+    if name.is_empty() {
+        Path::new(file).file_stem().and_then(|stem| stem.to_str()).unwrap_or(file).to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+// Niveles completados. El primero siempre está desbloqueado y cada nivel
+// completado desbloquea el siguiente.
+pub struct Progress {
+    completed: HashSet<String>,
+}
+
+impl Progress {
+    pub fn new() -> Progress {
+        Progress { completed: HashSet::new() }
+    }
+
+    // Sin archivo de progreso se empieza de cero
+    pub fn load(path: &str) -> Progress {
+        let text = fs::read_to_string(path).unwrap_or_default();
+        Progress::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Progress {
+        let mut progress = Progress::new();
+        for line in text.lines() {
+            if let Some(("completado", file)) = line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+                progress.completed.insert(file.to_string());
+            }
+        }
+        progress
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.encode())
+    }
+
+    pub fn encode(&self) -> String {
+        let mut files: Vec<&String> = self.completed.iter().collect();
+        files.sort();
+        files.iter().map(|file| format!("completado = {}\n", file)).collect()
+    }
+
+    pub fn complete(&mut self, file: &str) {
+        self.completed.insert(file.to_string());
+    }
+
+    pub fn is_completed(&self, file: &str) -> bool {
+        self.completed.contains(file)
+    }
+
+    pub fn is_unlocked(&self, campaign: &Campaign, index: usize) -> bool {
+        index == 0 || campaign.levels.get(index - 1).is_some_and(|previous| self.is_completed(&previous.file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_campaign_order_and_extra_levels() {
+        let extra = files(&["maze1.txt", "maze2.txt", "maze_generado.txt"]);
+        let campaign = Campaign::parse("maze2.txt = Pasillos\nmaze1.txt\nfalta.txt = Nada\n", &extra);
+        let order: Vec<&str> = campaign.levels.iter().map(|level| level.file.as_str()).collect();
+        assert_eq!(order, ["maze2.txt", "maze1.txt", "maze_generado.txt"]);
+        assert_eq!(campaign.levels[0].name, "Pasillos");
+        assert_eq!(campaign.levels[1].name, "maze1");
+        assert_eq!(campaign.next_after("maze1.txt").unwrap().file, "maze_generado.txt");
+        assert!(campaign.next_after("maze_generado.txt").is_none());
+    }
+
+    #[test]
+    fn test_completing_unlocks_next_level() {
+        let campaign = Campaign::parse("", &files(&["maze1.txt", "maze2.txt", "maze3.txt"]));
+        let mut progress = Progress::new();
+        assert!(progress.is_unlocked(&campaign, 0));
+        assert!(!progress.is_unlocked(&campaign, 1));
+
+        progress.complete("maze1.txt");
+        let progress = Progress::parse(&progress.encode());
+        assert!(progress.is_unlocked(&campaign, 1));
+        assert!(!progress.is_unlocked(&campaign, 2));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::game::TICK_RATE;
+use crate::rng::Rng;
+
+// Imágenes y sonidos (id, archivo) de los sustos. El primero de cada lista
+// viene con el juego; los demás se usan solo si existen.
+pub const SCARE_IMAGES: [&str; 3] = ["creepy.png", "susto2.png", "susto3.png"];
+pub const SCARE_SOUNDS: [(&str, &str); 3] = [("grito", "screamer.mp3"), ("grito2", "grito2.mp3"), ("grito3", "grito3.mp3")];
+
+// Cómo se altera la imagen y el grito de un susto, para que no sean siempre
+// iguales aunque solo estén los archivos que vienen con el juego
+pub struct ScareVariant {
+    pub flip: bool, // Imagen espejada
+    pub tint: u32,  // Se multiplica por cada color de la imagen
+    pub pitch: f32, // Tono del grito
+}
+
+// La primera es la imagen y el grito tal cual, la del enemigo al atrapar
+pub const SCARE_VARIANTS: [ScareVariant; 4] = [
+    ScareVariant { flip: false, tint: 0xFFFFFF, pitch: 1.0 },
+    ScareVariant { flip: true, tint: 0xFF7070, pitch: 0.8 },
+    ScareVariant { flip: false, tint: 0x90FF90, pitch: 1.25 },
+    ScareVariant { flip: true, tint: 0xA0A0FF, pitch: 0.65 },
+];
+
+// Tiempo mínimo entre dos sustos
+const MIN_GAP_TICKS: u64 = 8 * TICK_RATE as u64;
+// Distancia a la que un enemigo empieza a subir la tensión
+const NEAR_DISTANCE: f32 = 400.0;
+
+// Cuánto sube la tensión por tick: sola se llena en 30 segundos, con un enemigo
+// encima en 6 y con uno a la vista en 2
+const TENSION_RISE: f32 = 1.0 / (30 * TICK_RATE) as f32;
+const NEAR_RISE: f32 = 1.0 / (6 * TICK_RATE) as f32;
+const IN_VIEW_RISE: f32 = 1.0 / (2 * TICK_RATE) as f32;
+
+// Decide cuándo asustar. En lugar de un reloj fijo, la tensión sube con el
+// tiempo y con la cercanía de los enemigos, y cuanto más alta más probable es
+// el susto. Ver a un enemigo con la tensión ya alta lo hace casi seguro.
+pub struct ScareDirector {
+    pub tension: f32, // 0.0 a 1.0; vuelve a cero después de cada susto
+    pub last_scare_tick: u64,
+}
+
+impl ScareDirector {
+    pub fn new() -> ScareDirector {
+        ScareDirector { tension: 0.0, last_scare_tick: 0 }
+    }
+
+    // Avanza un tick. `nearest` es la distancia al enemigo más cercano e
+    // `in_view` si alguno está a la vista. Si toca un susto devuelve cuál de
+    // SCARE_VARIANTS usar.
+    pub fn update(&mut self, tick: u64, nearest: f32, in_view: bool, rng: &mut Rng) -> Option<u32> {
+        let closeness = (1.0 - nearest / NEAR_DISTANCE).max(0.0);
+        let mut rise = TENSION_RISE + closeness * NEAR_RISE;
+        if in_view {
+            rise += IN_VIEW_RISE;
+        }
+        self.tension = (self.tension + rise).min(1.0);
+
+        if tick < self.last_scare_tick + MIN_GAP_TICKS {
+            return None;
+        }
+        // Con la tensión al máximo hay un susto cada dos segundos en promedio
+        let mut chance = self.tension * self.tension / (2 * TICK_RATE) as f32;
+        if in_view && self.tension > 0.25 {
+            chance += 1.0 / (TICK_RATE / 2) as f32;
+        }
+        if rng.next_f32() >= chance {
+            return None;
+        }
+        self.tension = 0.0;
+        self.last_scare_tick = tick;
+        Some((rng.next_u64() % SCARE_VARIANTS.len() as u64) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ticks hasta el primer susto
+    fn first_scare(seed: u64, nearest: f32, in_view: bool) -> u64 {
+        let mut director = ScareDirector::new();
+        let mut rng = Rng::new(seed);
+        (1..).find(|&tick| director.update(tick, nearest, in_view, &mut rng).is_some()).unwrap()
+    }
+
+    #[test]
+    fn test_threats_bring_scares_sooner() {
+        // Suma de varias partidas, para no depender de una sola secuencia
+        let total = |nearest, in_view| (0..20).map(|seed| first_scare(seed, nearest, in_view)).sum::<u64>();
+        let alone = total(f32::INFINITY, false);
+        let near = total(100.0, false);
+        let seen = total(100.0, true);
+        assert!(seen < near && near < alone);
+        assert!(near >= 20 * MIN_GAP_TICKS);
+        // Un enemigo a la vista asusta apenas termina la pausa entre sustos
+        assert!(seen < 20 * (MIN_GAP_TICKS + TICK_RATE as u64));
+    }
+
+    #[test]
+    fn test_scare_resets_tension_and_waits() {
+        let mut director = ScareDirector::new();
+        let mut rng = Rng::new(5);
+        let scare = (1..).find(|&tick| director.update(tick, 50.0, true, &mut rng).is_some()).unwrap();
+        assert_eq!(director.tension, 0.0);
+        assert_eq!(director.last_scare_tick, scare);
+        let blocked = (scare + 1..scare + MIN_GAP_TICKS).any(|tick| director.update(tick, 50.0, true, &mut rng).is_some());
+        assert!(!blocked);
+    }
+
+    #[test]
+    fn test_scares_vary() {
+        let mut director = ScareDirector::new();
+        let mut rng = Rng::new(3);
+        let variants: Vec<u32> = (1..20 * MIN_GAP_TICKS).filter_map(|tick| director.update(tick, 50.0, true, &mut rng)).collect();
+        assert!(variants.len() >= 10);
+        assert!(variants.iter().all(|&variant| (variant as usize) < SCARE_VARIANTS.len()));
+        assert!(variants.iter().any(|&variant| variant != variants[0]));
+    }
+}
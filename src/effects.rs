@@ -0,0 +1,91 @@
+use crate::game::{Game, TICK_RATE};
+use crate::postprocess::{PostEffect, PostProcess};
+use crate::rng::Rng;
+
+// Efectos de pantalla según el estado del jugador
+#[derive(Default, PartialEq, Debug)]
+pub struct ScreenEffects {
+    pub vignette: f32,       // 0.0 a 1.0, cuánto se oscurecen los bordes
+    pub desaturation: f32,   // 0.0 a 1.0, cuánto color se pierde
+    pub shake: (f32, f32),   // Desplazamiento de la vista en píxeles
+    pub darkness: f32,       // Niebla mínima, para las zonas oscuras
+    pub flash: f32,          // 0.0 a 1.0, destello rojo al recibir un golpe
+}
+
+impl ScreenEffects {
+    // Con salud y cordura altas no hay efectos; empiezan por debajo de la mitad
+    pub fn of(game: &Game) -> ScreenEffects {
+        let fear = (1.0 - game.player.sanity * 2.0).max(0.0);
+        let injury = (1.0 - game.player.health * 2.0).max(0.0);
+
+        // La vista tiembla fuerte justo después de un golpe y un poco con poca salud
+        let hurt = game.hurt_until_tick.saturating_sub(game.tick) as f32 / (2 * TICK_RATE) as f32;
+        let amplitude = 8.0 * hurt + 2.0 * injury;
+        let shake = if amplitude > 0.0 {
+            let mut rng = Rng::new(game.tick);
+            (rng.range(-amplitude, amplitude), rng.range(-amplitude, amplitude))
+        } else {
+            (0.0, 0.0)
+        };
+
+        ScreenEffects {
+            vignette: fear.max(injury),
+            desaturation: fear,
+            shake,
+            darkness: game.darkness(),
+            // El destello se apaga rápido, mucho antes que el temblor
+            flash: 0.6 * hurt.powi(4),
+        }
+    }
+
+    // Efectos del cuadro: primero los del nivel y después los del jugador,
+    // que siempre se ven porque avisan cuánta salud y cordura quedan
+    pub fn post_process(&self, level: &[PostEffect], tick: u64) -> PostProcess {
+        let mut effects = level.to_vec();
+        if self.desaturation > 0.0 {
+            effects.push(PostEffect::Desaturate(self.desaturation));
+        }
+        if self.vignette > 0.0 {
+            effects.push(PostEffect::Vignette(self.vignette));
+        }
+        if self.flash > 0.0 {
+            effects.push(PostEffect::Flash(0xCC0000, self.flash));
+        }
+        PostProcess { effects, seed: tick }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_healthy_player_has_no_effects() {
+        let mut game = Game::new("maze1.txt", 1);
+        assert_eq!(ScreenEffects::of(&game), ScreenEffects::default());
+
+        game.player.sanity = 0.0;
+        game.player.health = 0.1;
+        let effects = ScreenEffects::of(&game);
+        assert_eq!(effects.desaturation, 1.0);
+        assert!(effects.vignette > 0.0);
+        assert!(effects.shake != (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_player_effects_follow_level_effects() {
+        let mut game = Game::new("maze1.txt", 1);
+        let level = [PostEffect::Grain(0.1)];
+        let healthy = ScreenEffects::of(&game).post_process(&level, 7);
+        assert_eq!(healthy.effects.len(), 1);
+        assert_eq!(healthy.seed, 7);
+
+        // Justo después de un golpe hay destello, al final de la cadena
+        game.player.sanity = 0.2;
+        game.hurt_until_tick = game.tick + 2 * TICK_RATE as u64;
+        let hurt = ScreenEffects::of(&game).post_process(&level, 7);
+        assert!(matches!(hurt.effects[0], PostEffect::Grain(_)));
+        assert!(matches!(hurt.effects[1], PostEffect::Desaturate(_)));
+        assert!(matches!(hurt.effects.last(), Some(PostEffect::Flash(_, _))));
+    }
+}
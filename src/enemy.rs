@@ -0,0 +1,181 @@
+use nalgebra_glm::Vec2;
+use crate::game::cell_center;
+use crate::navigation::{cell_at, find_path, line_of_sight, Cell};
+
+// Distancia en píxeles a la que un enemigo ve al jugador si no hay paredes en medio
+const SIGHT_DISTANCE: f32 = 350.0;
+// Distancia a la que el enemigo atrapa al jugador
+const CATCH_DISTANCE: f32 = 25.0;
+// Alcance del ruido de cada paso del jugador
+pub const WALK_NOISE: f32 = 150.0;
+pub const SPRINT_NOISE: f32 = 350.0;
+// Ticks que el enemigo se queda buscando antes de volver a patrullar
+const INVESTIGATE_TICKS: u32 = 180;
+const LOST_TRACK_TICKS: u32 = 120;
+
+// Enemigo tal como lo describe la configuración del nivel
+pub struct EnemySpawn {
+    pub patrol: Vec<Cell>, // La primera celda es donde aparece
+    pub speed: f32,        // Píxeles por tick al patrullar o investigar
+    pub chase_speed: f32,  // Píxeles por tick al perseguir
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnemyState {
+    Patrol,      // Recorre sus celdas de patrulla en orden
+    Investigate, // Va hacia donde escuchó un ruido
+    Chase,       // Ve al jugador y lo sigue
+    LostTrack,   // Perdió al jugador y se queda buscando
+}
+
+impl EnemyState {
+    pub const ALL: [EnemyState; 4] = [EnemyState::Patrol, EnemyState::Investigate, EnemyState::Chase, EnemyState::LostTrack];
+}
+
+// Estado de un enemigo en la partida. La configuración queda en el nivel, así
+// que aquí solo está lo que cambia y lo que hay que guardar.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Enemy {
+    pub pos: Vec2,
+    pub state: EnemyState,
+    pub target: Cell,       // Celda que investiga o donde vio al jugador por última vez
+    pub wait: u32,          // Ticks que le quedan buscando
+    pub patrol_index: usize,
+    pub path: Vec<Cell>,    // Celdas que le faltan para llegar a su destino
+}
+
+impl Enemy {
+    pub fn new(spawn: &EnemySpawn, block_size: usize) -> Enemy {
+        let start = spawn.patrol[0];
+        Enemy {
+            pos: cell_center(start, block_size),
+            state: EnemyState::Patrol,
+            target: start,
+            wait: 0,
+            patrol_index: 0,
+            path: Vec::new(),
+        }
+    }
+
+    // Avanza un tick. `noise` es el alcance del ruido que hizo el jugador en este
+    // tick, 0.0 si no hizo ruido. Devuelve true si atrapó al jugador.
+    pub fn update(&mut self, spawn: &EnemySpawn, maze: &[Vec<char>], player: Vec2, noise: f32, block_size: usize) -> bool {
+        let distance = nalgebra_glm::distance(&self.pos, &player);
+        let sees = distance <= SIGHT_DISTANCE && line_of_sight(maze, self.pos, player, block_size);
+        let player_cell = cell_at(player, block_size);
+        if sees {
+            self.state = EnemyState::Chase;
+            self.target = player_cell;
+        } else if distance <= noise && self.state != EnemyState::Chase {
+            self.state = EnemyState::Investigate;
+            self.target = player_cell;
+            self.wait = INVESTIGATE_TICKS;
+        }
+
+        let here = cell_at(self.pos, block_size);
+        let goal = match self.state {
+            EnemyState::Patrol => spawn.patrol[self.patrol_index],
+            EnemyState::LostTrack => here,
+            EnemyState::Investigate | EnemyState::Chase => self.target,
+        };
+        if here != goal && self.path.last() != Some(&goal) {
+            // Sin camino posible se comporta como si ya hubiera llegado
+            self.path = find_path(maze, here, goal).unwrap_or_default();
+        }
+
+        let speed = if self.state == EnemyState::Chase { spawn.chase_speed } else { spawn.speed };
+        if let Some(&next) = self.path.first() {
+            self.move_towards(cell_center(next, block_size), speed);
+            if self.pos == cell_center(next, block_size) {
+                self.path.remove(0);
+            }
+        } else {
+            match self.state {
+                EnemyState::Patrol => self.patrol_index = (self.patrol_index + 1) % spawn.patrol.len(),
+                // En la misma celda que el jugador ya no hace falta el camino
+                EnemyState::Chase if sees => self.move_towards(player, speed),
+                EnemyState::Chase => {
+                    self.state = EnemyState::LostTrack;
+                    self.wait = LOST_TRACK_TICKS;
+                }
+                EnemyState::Investigate | EnemyState::LostTrack => {
+                    if self.wait == 0 {
+                        self.state = EnemyState::Patrol;
+                    } else {
+                        self.wait -= 1;
+                    }
+                }
+            }
+        }
+
+        nalgebra_glm::distance(&self.pos, &player) <= CATCH_DISTANCE
+    }
+
+    fn move_towards(&mut self, to: Vec2, speed: f32) {
+        let delta = to - self.pos;
+        if delta.norm() <= speed {
+            self.pos = to;
+        } else {
+            self.pos += delta.normalize() * speed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Un pasillo largo con una habitación detrás de una pared
+    fn maze() -> Vec<Vec<char>> {
+        vec![
+            "+--+--+--+--+".chars().collect(),
+            "|           |".chars().collect(),
+            "+--+--+--+  +".chars().collect(),
+            "|           |".chars().collect(),
+            "+--+--+--+--+".chars().collect(),
+        ]
+    }
+
+    fn spawn() -> EnemySpawn {
+        EnemySpawn { patrol: vec![(1, 3), (4, 3)], speed: 2.0, chase_speed: 4.0 }
+    }
+
+    fn run(enemy: &mut Enemy, player: Vec2, noise: f32, ticks: usize) -> bool {
+        let (maze, spawn) = (maze(), spawn());
+        (0..ticks).any(|_| enemy.update(&spawn, &maze, player, noise, 50))
+    }
+
+    #[test]
+    fn test_hears_noise_and_catches_player() {
+        let mut enemy = Enemy::new(&spawn(), 50);
+        // El jugador está al otro lado de la pared, fuera de la vista
+        let player = Vec2::new(75.0, 75.0);
+        run(&mut enemy, player, 0.0, 10);
+        assert_eq!(enemy.state, EnemyState::Patrol);
+
+        run(&mut enemy, player, SPRINT_NOISE, 1);
+        assert_eq!(enemy.state, EnemyState::Investigate);
+        assert_eq!(enemy.target, (1, 1));
+
+        // Camina hasta el pasillo de arriba, lo ve y lo atrapa
+        assert!(run(&mut enemy, player, 0.0, 600));
+        assert_eq!(enemy.state, EnemyState::Chase);
+    }
+
+    #[test]
+    fn test_chase_loses_track_and_returns_to_patrol() {
+        let mut enemy = Enemy::new(&spawn(), 50);
+        // Visible más adelante en el mismo pasillo
+        assert!(!run(&mut enemy, Vec2::new(375.0, 175.0), 0.0, 1));
+        assert_eq!(enemy.state, EnemyState::Chase);
+        assert_eq!(enemy.target, (7, 3));
+
+        // El jugador se escapa por la esquina; el enemigo va a la última celda donde lo vio
+        let hidden = Vec2::new(75.0, 75.0);
+        assert!(!run(&mut enemy, hidden, 0.0, 1));
+        run(&mut enemy, hidden, 0.0, 150);
+        assert_eq!(enemy.state, EnemyState::LostTrack);
+        run(&mut enemy, hidden, 0.0, LOST_TRACK_TICKS as usize + 1);
+        assert_eq!(enemy.state, EnemyState::Patrol);
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use once_cell::sync::Lazy;
+use rusttype::{point, Font, GlyphId, PositionedGlyph, Scale};
+
+// Fuentes incluidas en el juego. Se leen una sola vez, la primera vez que se usan.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FontId {
+    Nasa,  // Texto general
+    Sixty, // Títulos
+}
+
+static FONTS: Lazy<[Font<'static>; 2]> = Lazy::new(|| {
+    [
+        Font::try_from_bytes(include_bytes!("../assets/Nasa.ttf") as &[u8]).expect("Nasa.ttf no es una fuente válida"),
+        Font::try_from_bytes(include_bytes!("../assets/SIXTY.TTF") as &[u8]).expect("SIXTY.TTF no es una fuente válida"),
+    ]
+});
+
+impl FontId {
+    pub fn font(self) -> &'static Font<'static> {
+        &FONTS[self as usize]
+    }
+}
+
+// Cada glifo se guarda con el desplazamiento horizontal redondeado a cuartos de
+// píxel; más precisión no se nota y llenaría la caché
+const SUBPIXEL_STEPS: f32 = 4.0;
+// Si la caché crece más que esto se vacía; solo pasa con muchos tamaños distintos
+const MAX_GLYPHS: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct GlyphKey {
+    font: FontId,
+    glyph: GlyphId,
+    scale: (u32, u32), // Bits de la escala, para poder usarla como clave
+    subpixel: u8,
+}
+
+// Glifo ya dibujado: cobertura de 0 a 255 de cada píxel de su rectángulo
+pub struct CachedGlyph {
+    pub left: i32, // Esquina del rectángulo respecto al origen del glifo
+    pub top: i32,
+    pub width: usize,
+    pub height: usize,
+    pub coverage: Vec<u8>,
+}
+
+// Glifos dibujados, para no volver a dibujarlos en cada cuadro
+pub struct GlyphCache {
+    glyphs: HashMap<GlyphKey, CachedGlyph>,
+}
+
+impl GlyphCache {
+    pub fn new() -> GlyphCache {
+        GlyphCache { glyphs: HashMap::new() }
+    }
+
+    pub fn count(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    // Devuelve el glifo dibujado y la posición entera en pantalla de su origen
+    pub fn get(&mut self, font: FontId, glyph: &PositionedGlyph, scale: Scale) -> (&CachedGlyph, i32, i32) {
+        let position = glyph.position();
+        let mut x = position.x.floor();
+        let mut subpixel = ((position.x - x) * SUBPIXEL_STEPS).round() as u8;
+        if subpixel as f32 >= SUBPIXEL_STEPS {
+            x += 1.0;
+            subpixel = 0;
+        }
+        let key = GlyphKey { font, glyph: glyph.id(), scale: (scale.x.to_bits(), scale.y.to_bits()), subpixel };
+
+        if self.glyphs.len() >= MAX_GLYPHS && !self.glyphs.contains_key(&key) {
+            self.glyphs.clear();
+        }
+        let cached = self.glyphs.entry(key).or_insert_with(|| {
+            let offset = point(subpixel as f32 / SUBPIXEL_STEPS, 0.0);
+            rasterize(&font.font().glyph(key.glyph).scaled(scale).positioned(offset))
+        });
+        (cached, x as i32, position.y.round() as i32)
+    }
+}
+
+fn rasterize(glyph: &PositionedGlyph) -> CachedGlyph {
+    let Some(bounds) = glyph.pixel_bounding_box() else {
+        // Los espacios no tienen píxeles
+        return CachedGlyph { left: 0, top: 0, width: 0, height: 0, coverage: Vec::new() };
+    };
+    let width = bounds.width() as usize;
+    let height = bounds.height() as usize;
+    let mut coverage = vec![0; width * height];
+    glyph.draw(|gx, gy, value| {
+        coverage[gy as usize * width + gx as usize] = (value * 255.0).round() as u8;
+    });
+    CachedGlyph { left: bounds.min.x, top: bounds.min.y, width, height, coverage }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::Framebuffer;
+
+    #[test]
+    fn test_glyphs_are_reused() {
+        let mut framebuffer = Framebuffer::new(300, 60);
+        framebuffer.drawtext("abab", 0, 0, FontId::Nasa, Scale::uniform(20.0), 0xFFFFFF);
+        let first = framebuffer.buffer.clone();
+        let cached = framebuffer.cached_glyphs();
+        assert!(cached > 0 && cached <= 4 * SUBPIXEL_STEPS as usize);
+
+        framebuffer.clear();
+        framebuffer.drawtext("abab", 0, 0, FontId::Nasa, Scale::uniform(20.0), 0xFFFFFF);
+        assert_eq!(framebuffer.cached_glyphs(), cached);
+        assert_eq!(framebuffer.buffer, first);
+    }
+
+    #[test]
+    fn test_fonts_and_scales_are_cached_apart() {
+        let mut framebuffer = Framebuffer::new(300, 60);
+        framebuffer.drawtext("a", 0, 0, FontId::Nasa, Scale::uniform(20.0), 0xFFFFFF);
+        framebuffer.drawtext("a", 0, 0, FontId::Sixty, Scale::uniform(20.0), 0xFFFFFF);
+        framebuffer.drawtext("a", 0, 0, FontId::Nasa, Scale::uniform(30.0), 0xFFFFFF);
+        assert_eq!(framebuffer.cached_glyphs(), 3);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::game::TICK_RATE;
+use crate::level::Surface;
+use crate::rng::Rng;
+
+// Distancia en píxeles entre un paso y el siguiente
+const WALK_STRIDE: f32 = 45.0;
+const SPRINT_STRIDE: f32 = 60.0;
+// Cuadros quieto, unos 300 ms, para que el siguiente paso suene apenas vuelva
+// a moverse. Las pausas más cortas no cortan la zancada.
+const STOP_TICKS: u32 = TICK_RATE * 3 / 10;
+
+// Un paso que debe sonar
+#[derive(PartialEq, Debug)]
+pub struct Step {
+    pub surface: Surface,
+    pub left: bool,
+    pub volume: f32,
+    pub pitch: f32,
+}
+
+impl Step {
+    // Nombre del sonido específico para la superficie y el pie
+    pub fn sound_id(&self) -> String {
+        format!("pasos_{}_{}", self.surface.name(), self.side())
+    }
+
+    // Sonido genérico del pie, para superficies sin muestras propias
+    pub fn fallback_sound_id(&self) -> String {
+        format!("pasos_{}", self.side())
+    }
+
+    // Volumen y tono para el paso genérico, ajustados según la superficie
+    pub fn fallback_tone(&self) -> (f32, f32) {
+        let (volume, pitch) = match self.surface {
+            Surface::Piedra => (1.0, 1.0),
+            Surface::Madera => (1.1, 1.15),
+            Surface::Agua => (0.8, 0.75),
+            Surface::Tierra => (0.7, 0.9),
+        };
+        (self.volume * volume, self.pitch * pitch)
+    }
+
+    fn side(&self) -> &'static str {
+        if self.left { "izq" } else { "der" }
+    }
+}
+
+// Acumula la distancia recorrida y emite un paso cada zancada
+pub struct Footsteps {
+    pub distance: f32,
+    pub left: bool,
+    pub still_ticks: u32,
+}
+
+impl Footsteps {
+    pub fn new() -> Self {
+        // El primer paso suena apenas el jugador empieza a moverse
+        Footsteps { distance: WALK_STRIDE, left: true, still_ticks: 0 }
+    }
+
+    pub fn update(&mut self, travelled: f32, sprinting: bool, surface: Surface, rng: &mut Rng) -> Option<Step> {
+        if travelled <= 0.0 {
+            // Detenido un rato, el siguiente paso suena en cuanto vuelva a moverse
+            self.still_ticks += 1;
+            if self.still_ticks >= STOP_TICKS {
+                self.distance = WALK_STRIDE;
+            }
+            return None;
+        }
+        self.still_ticks = 0;
+
+        self.distance += travelled;
+        let stride = if sprinting { SPRINT_STRIDE } else { WALK_STRIDE };
+        if self.distance < stride {
+            return None;
+        }
+        self.distance = 0.0;
+        self.left = !self.left;
+
+        let base_volume = if sprinting { 0.9 } else { 0.6 };
+        Some(Step {
+            surface,
+            left: self.left,
+            volume: base_volume * rng.range(0.85, 1.15),
+            pitch: rng.range(0.92, 1.08),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_alternate_and_follow_distance() {
+        let mut footsteps = Footsteps::new();
+        let mut rng = Rng::new(1);
+        let mut steps = Vec::new();
+        for _ in 0..60 {
+            if let Some(step) = footsteps.update(5.0, false, Surface::Piedra, &mut rng) {
+                steps.push(step);
+            }
+        }
+        // 300 píxeles: el primer paso inmediato y luego uno cada 45
+        assert_eq!(steps.len(), 7);
+        assert!(steps.windows(2).all(|pair| pair[0].left != pair[1].left));
+    }
+
+    #[test]
+    fn test_sprint_has_longer_stride() {
+        let mut walk = Footsteps::new();
+        let mut sprint = Footsteps::new();
+        let mut rng = Rng::new(1);
+        let mut walk_steps = 0;
+        let mut sprint_steps = 0;
+        for _ in 0..60 {
+            walk_steps += walk.update(5.0, false, Surface::Piedra, &mut rng).is_some() as u32;
+            sprint_steps += sprint.update(5.0, true, Surface::Piedra, &mut rng).is_some() as u32;
+        }
+        assert!(sprint_steps < walk_steps);
+    }
+
+    #[test]
+    fn test_tapping_keeps_the_stride() {
+        let mut footsteps = Footsteps::new();
+        let mut rng = Rng::new(1);
+        let mut steps = 0;
+        // Toques de tres cuadros con pausas cortas: 30 toques de 15 píxeles
+        for _ in 0..30 {
+            for _ in 0..3 {
+                steps += footsteps.update(5.0, false, Surface::Piedra, &mut rng).is_some() as u32;
+            }
+            for _ in 0..5 {
+                steps += footsteps.update(0.0, false, Surface::Piedra, &mut rng).is_some() as u32;
+            }
+        }
+        // 450 píxeles: el primer paso y uno cada 45, igual que sin pausas
+        assert_eq!(steps, 10);
+
+        // Después de una pausa larga el paso vuelve a sonar enseguida
+        for _ in 0..STOP_TICKS {
+            footsteps.update(0.0, false, Surface::Piedra, &mut rng);
+        }
+        assert!(footsteps.update(5.0, false, Surface::Piedra, &mut rng).is_some());
+    }
+}
@@ -1,12 +1,69 @@
 use crate::bmp::write_bmp_file;
-use rusttype::{Font, Scale, point};
+use crate::fonts::{FontId, GlyphCache};
+use crate::postprocess::PostProcess;
+use crate::text::{layout, PlacedGlyph, Span, TextBounds, TextStyle};
+use crate::textures::{AddressMode, Texture};
+use rusttype::Scale;
+
+// Rectángulo en pantalla o dentro de una textura
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: usize, height: usize) -> Rect {
+        Rect { x, y, width, height }
+    }
+}
+
+// Cómo se combina lo que se dibuja con lo que ya hay en pantalla
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlendMode {
+    Alpha,    // Tapa según la transparencia; opaco reemplaza
+    Additive, // Suma la luz, para brillos
+    Multiply, // Oscurece, para sombras y tintes
+}
+
+// Capas de la imagen, de abajo hacia arriba. El mundo es opaco; las demás
+// guardan la transparencia de cada píxel (0xAARRGGBB) y empiezan vacías.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LayerId {
+    World,   // Paredes, piso y menús
+    Sprites, // Enemigos en 3D
+    Hud,     // Minimapa, tiempo y barras
+    Overlay, // Sustos y pausa
+    Debug,   // FPS
+}
+
+impl LayerId {
+    pub const ALL: [LayerId; 5] = [LayerId::World, LayerId::Sprites, LayerId::Hud, LayerId::Overlay, LayerId::Debug];
+}
+
+struct Layer {
+    pixels: Vec<u32>, // Vacío mientras la capa está activa; sus píxeles están en `buffer`
+    visible: bool,
+    opacity: f32,
+    used: bool, // Si se dibujó algo desde el último begin_frame
+}
 
 pub struct Framebuffer {
     pub width: usize,  // Ancho del framebuffer
     pub height: usize, // Alto del framebuffer
-    pub buffer: Vec<u32>,  // Buffer de píxeles
+    pub buffer: Vec<u32>,  // Buffer de píxeles de la capa activa
     background_color: u32, // Color de fondo del framebuffer
     current_color: u32,    // Color actual del framebuffer
+    glyphs: GlyphCache,    // Glifos ya dibujados del texto
+    blend_mode: BlendMode, // Modo con el que se combina todo lo que se dibuja
+    opacity: f32,          // Opacidad de todo lo que se dibuja, de 0.0 a 1.0
+    layers: Vec<Layer>,    // Una por cada LayerId, en el mismo orden
+    current: LayerId,      // Capa en la que se dibuja
+    solo: Option<LayerId>, // Para depurar: mostrar solo esta capa
+    frame: Vec<u32>,       // Resultado de combinar las capas
+    post: Option<PostProcess>, // Efectos sobre el mundo, antes de agregar el HUD
 }
 
 impl Framebuffer {
@@ -21,20 +78,130 @@ impl Framebuffer {
             buffer, // Asigna el vector de píxeles al campo buffer de la estructura
             background_color, // Asigna el color de fondo proporcionado al campo background_color de la estructura
             current_color: 0xFFFFFF, // Color actual predeterminado (blanco)
+            glyphs: GlyphCache::new(),
+            blend_mode: BlendMode::Alpha,
+            opacity: 1.0,
+            layers: LayerId::ALL
+                .iter()
+                .map(|&id| Layer {
+                    pixels: if id == LayerId::World { Vec::new() } else { vec![0; buffer_size] },
+                    visible: true,
+                    opacity: 1.0,
+                    used: id == LayerId::World,
+                })
+                .collect(),
+            current: LayerId::World,
+            solo: None,
+            frame: vec![background_color; buffer_size],
+            post: None,
         }
     }
 
-    // Función para limpiar el framebuffer
+    // Función para limpiar el framebuffer; las capas de arriba quedan transparentes
     pub fn clear(&mut self) {
         // Usa el método fill de Vec para llenar todo el buffer con el color de fondo
-        self.buffer.fill(self.background_color);
+        let color = if self.current == LayerId::World { self.background_color } else { 0 };
+        self.buffer.fill(color);
+    }
+
+    // Empieza un cuadro: vacía las capas usadas y vuelve a dibujar en el mundo
+    pub fn begin_frame(&mut self) {
+        self.set_layer(LayerId::World);
+        self.post = None;
+        for layer in &mut self.layers[1..] {
+            if layer.used {
+                layer.pixels.fill(0);
+                layer.used = false;
+            }
+        }
+    }
+
+    // Cambia la capa en la que se dibuja. Los píxeles de la capa activa se
+    // mueven a `buffer`, así todo el código que dibuja sigue igual.
+    pub fn set_layer(&mut self, id: LayerId) {
+        if id != self.current {
+            std::mem::swap(&mut self.buffer, &mut self.layers[self.current as usize].pixels);
+            std::mem::swap(&mut self.buffer, &mut self.layers[id as usize].pixels);
+            self.current = id;
+        }
+        self.layers[id as usize].used = true;
+    }
+
+    pub fn set_layer_visible(&mut self, id: LayerId, visible: bool) {
+        self.layers[id as usize].visible = visible;
+    }
+
+    pub fn is_layer_visible(&self, id: LayerId) -> bool {
+        self.layers[id as usize].visible
+    }
+
+    pub fn set_layer_opacity(&mut self, id: LayerId, opacity: f32) {
+        self.layers[id as usize].opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    // Pasa a mostrar una sola capa, la siguiente cada vez, y al final todas
+    pub fn cycle_solo_layer(&mut self) -> Option<LayerId> {
+        self.solo = match self.solo {
+            None => Some(LayerId::ALL[0]),
+            Some(id) => LayerId::ALL.get(id as usize + 1).copied(),
+        };
+        self.solo
+    }
+
+    pub fn solo_layer(&self) -> Option<LayerId> {
+        self.solo
+    }
+
+    // Efectos para este cuadro. Se aplican al combinar las capas, sobre el
+    // mundo y los enemigos pero no sobre el HUD ni los sustos.
+    pub fn set_post_process(&mut self, post: PostProcess) {
+        self.post = Some(post);
+    }
+
+    fn pixels(&self, id: LayerId) -> &[u32] {
+        if id == self.current { &self.buffer } else { &self.layers[id as usize].pixels }
+    }
+
+    // Combina las capas visibles en la imagen final, una vez por cuadro
+    pub fn compose(&mut self) -> &[u32] {
+        let mut frame = std::mem::take(&mut self.frame);
+        frame.fill(0);
+        for id in LayerId::ALL {
+            if let (LayerId::Hud, Some(post), None) = (id, &self.post, self.solo) {
+                post.apply(&mut frame, self.width, self.height);
+            }
+            let layer = &self.layers[id as usize];
+            let shown = match self.solo {
+                Some(solo) => solo == id,
+                None => layer.visible,
+            };
+            if !shown || !layer.used || layer.opacity <= 0.0 {
+                continue;
+            }
+            let opacity = (layer.opacity * 255.0).round() as u32;
+            for (out, &pixel) in frame.iter_mut().zip(self.pixels(id)) {
+                // El mundo es opaco aunque su byte de transparencia esté en cero
+                let alpha = if id == LayerId::World { 255 } else { pixel >> 24 };
+                let alpha = alpha * opacity / 255;
+                if alpha > 0 {
+                    *out = blend(*out, pixel, alpha as u8);
+                }
+            }
+        }
+        self.frame = frame;
+        &self.frame
+    }
+
+    // Última imagen combinada
+    pub fn frame(&self) -> &[u32] {
+        &self.frame
     }
 
     // Función para establecer un punto en el framebuffer
-    pub fn point(&mut self, x: usize, y: usize) {
+    pub fn point(&mut self, x: isize, y: isize) {
         if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
             let index = (y as usize) * self.width + (x as usize); // Calcula el índice en el buffer para el punto (x, y)
-            self.buffer[index] = self.current_color; // Establece el color actual en el punto correspondiente en el buffer
+            self.put(index, self.current_color, 255); // Establece el color actual en el punto correspondiente en el buffer
         }
     }
 
@@ -58,51 +225,340 @@ impl Framebuffer {
         self.current_color = color; // Actualiza el color actual del framebuffer
     }
 
-    pub fn drawtext(&mut self, text: &str, x: usize, y: usize, scale: Scale, color: u32) {
-        let font_data = include_bytes!("../assets/Nasa.ttf") as &[u8];
-        let font = Font::try_from_bytes(font_data).unwrap();
-        let v_metrics = font.v_metrics(scale);
-    
-        let line_height = (v_metrics.ascent - v_metrics.descent) as usize;
-    
-        // Dividir el texto en líneas
-        let lines: Vec<&str> = text.lines().collect();
-        let mut current_y = y;
-    
-        for line in lines {
-            let offset = point(x as f32, current_y as f32 + v_metrics.ascent);
-    
-            let glyphs: Vec<_> = font.layout(line, scale, offset).collect();
-    
-            for glyph in glyphs {
-                if let Some(bounding_box) = glyph.pixel_bounding_box() {
-                    glyph.draw(|gx, gy, gv| {
-                        if gv > 0.5 {
-                            let gx = gx as i32 + bounding_box.min.x;
-                            let gy = gy as i32 + bounding_box.min.y;
-    
-                            if gx >= 0 && gx < self.width as i32 && gy >= 0 && gy < self.height as i32 {
-                                let gx = gx as usize;
-                                let gy = gy as usize;
-    
-                                self.buffer[gy * self.width + gx] = color;
-                            }
-                        }
-                    });
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    // Opacidad de lo que se dibuje después; sirve para fundidos y paneles translúcidos
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    // Todas las escrituras pasan por acá: aplica el modo de mezcla y la
+    // opacidad global. Los colores son 0xRRGGBB y la transparencia va aparte.
+    fn put(&mut self, index: usize, color: u32, alpha: u8) {
+        self.buffer[index] = self.pen().mix(self.buffer[index], color, alpha);
+    }
+
+    fn pen(&self) -> Pen {
+        Pen { mode: self.blend_mode, opacity: self.opacity, layered: self.current != LayerId::World }
+    }
+
+    // Las figuras se recortan contra los bordes, así que pueden salirse de la
+    // pantalla. Reciben su color en lugar de usar el color actual.
+
+    // Pinta un píxel mezclando el color con lo que ya hay; alpha 255 lo tapa
+    pub fn blend_point(&mut self, x: i32, y: i32, color: u32, alpha: u8) {
+        if alpha > 0 && x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.put(y as usize * self.width + x as usize, color, alpha);
+        }
+    }
+
+    // Línea horizontal de x0 a x1 inclusive
+    fn hline(&mut self, x0: i32, x1: i32, y: i32, color: u32) {
+        if y < 0 || y >= self.height as i32 {
+            return;
+        }
+        let first = x0.min(x1).max(0);
+        let last = x0.max(x1).min(self.width as i32 - 1);
+        if first > last {
+            return;
+        }
+        let row = y as usize * self.width;
+        let pen = self.pen();
+        if pen.is_opaque() {
+            // Con opacidad total y mezcla normal se puede copiar sin leer lo de abajo
+            self.buffer[row + first as usize..=row + last as usize].fill(pen.mix(0, color, 255));
+        } else {
+            for index in row + first as usize..=row + last as usize {
+                self.put(index, color, 255);
+            }
+        }
+    }
+
+    // Línea de un píxel de ancho con el algoritmo de Bresenham
+    pub fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: u32) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (step_x, step_y) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y, mut error) = (x0, y0, dx + dy);
+        loop {
+            self.blend_point(x, y, color, 255);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let double = 2 * error;
+            if double >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if double <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    // Línea suavizada con el algoritmo de Wu: cada paso reparte el color entre
+    // los dos píxeles más cercanos según la distancia a cada uno
+    pub fn line_aa(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, color: u32) {
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        // Se recorre siempre por el eje más largo y de menor a mayor
+        let (mut x0, mut y0, mut x1, mut y1) = if steep { (y0, x0, y1, x1) } else { (x0, y0, x1, y1) };
+        if x0 > x1 {
+            (x0, x1, y0, y1) = (x1, x0, y1, y0);
+        }
+        let gradient = if x1 - x0 > 0.0 { (y1 - y0) / (x1 - x0) } else { 1.0 };
+        let mut plot = |major: i32, minor: i32, coverage: f32| {
+            let alpha = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+            if steep {
+                self.blend_point(minor, major, color, alpha);
+            } else {
+                self.blend_point(major, minor, color, alpha);
+            }
+        };
+        let first = x0.round() as i32;
+        let last = x1.round() as i32;
+        for major in first..=last {
+            let minor = y0 + gradient * (major as f32 - x0);
+            let fraction = minor - minor.floor();
+            plot(major, minor.floor() as i32, 1.0 - fraction);
+            plot(major, minor.floor() as i32 + 1, fraction);
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: usize, height: usize, color: u32) {
+        if width == 0 {
+            return;
+        }
+        for row in y.max(0)..(y + height as i32).min(self.height as i32) {
+            self.hline(x, x + width as i32 - 1, row, color);
+        }
+    }
+
+    // Borde de un píxel por dentro del rectángulo
+    pub fn rect(&mut self, x: i32, y: i32, width: usize, height: usize, color: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let (right, bottom) = (x + width as i32 - 1, y + height as i32 - 1);
+        self.hline(x, right, y, color);
+        self.hline(x, right, bottom, color);
+        self.fill_rect(x, y, 1, height, color);
+        self.fill_rect(right, y, 1, height, color);
+    }
+
+    // Circunferencia con el algoritmo del punto medio
+    pub fn circle(&mut self, cx: i32, cy: i32, radius: i32, color: u32) {
+        let (mut x, mut y, mut error) = (radius, 0, 1 - radius);
+        while x >= y {
+            for (px, py) in [(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+                self.blend_point(cx + px, cy + py, color, 255);
+            }
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    pub fn fill_circle(&mut self, cx: i32, cy: i32, radius: i32, color: u32) {
+        for dy in -radius..=radius {
+            let half = ((radius * radius - dy * dy) as f32).sqrt() as i32;
+            self.hline(cx - half, cx + half, cy + dy, color);
+        }
+    }
+
+    pub fn triangle(&mut self, a: (i32, i32), b: (i32, i32), c: (i32, i32), color: u32) {
+        self.polygon(&[a, b, c], color);
+    }
+
+    pub fn fill_triangle(&mut self, a: (i32, i32), b: (i32, i32), c: (i32, i32), color: u32) {
+        self.fill_polygon(&[a, b, c], color);
+    }
+
+    // Contorno cerrado que une los puntos en orden
+    pub fn polygon(&mut self, points: &[(i32, i32)], color: u32) {
+        for (index, &(x0, y0)) in points.iter().enumerate() {
+            let (x1, y1) = points[(index + 1) % points.len()];
+            self.line(x0, y0, x1, y1, color);
+        }
+    }
+
+    // Relleno por líneas horizontales: en cada fila se buscan los cruces con
+    // los lados y se pinta entre cada par. Sirve también para polígonos cóncavos.
+    pub fn fill_polygon(&mut self, points: &[(i32, i32)], color: u32) {
+        if points.len() < 3 {
+            return;
+        }
+        let top = points.iter().map(|p| p.1).min().unwrap().max(0);
+        let bottom = points.iter().map(|p| p.1).max().unwrap().min(self.height as i32 - 1);
+        let mut crossings = Vec::new();
+        for y in top..=bottom {
+            // Se mide en el centro de la fila para no contar dos veces los vértices
+            let center = y as f32 + 0.5;
+            crossings.clear();
+            for (index, &(x0, y0)) in points.iter().enumerate() {
+                let (x1, y1) = points[(index + 1) % points.len()];
+                let (y0, y1, x0, x1) = (y0 as f32, y1 as f32, x0 as f32, x1 as f32);
+                if (y0 <= center) != (y1 <= center) {
+                    crossings.push(x0 + (center - y0) / (y1 - y0) * (x1 - x0));
+                }
+            }
+            crossings.sort_by(f32::total_cmp);
+            for pair in crossings.chunks_exact(2) {
+                let first = pair[0].round() as i32;
+                let last = pair[1].round() as i32 - 1;
+                if first <= last {
+                    self.hline(first, last, y, color);
+                }
+            }
+        }
+    }
+
+    // Copia una parte de la textura (toda si `source` es None) estirada al
+    // rectángulo `dest`, respetando su transparencia. Los píxeles del color
+    // clave no se copian.
+    pub fn blit(&mut self, texture: &Texture, source: Option<Rect>, dest: Rect, color_key: Option<u32>) {
+        let source = source.unwrap_or(Rect::new(0, 0, texture.width as usize, texture.height as usize));
+        if source.width == 0 || source.height == 0 {
+            return;
+        }
+        let first_x = dest.x.max(0);
+        let last_x = (dest.x + dest.width as i32).min(self.width as i32);
+        let first_y = dest.y.max(0);
+        let last_y = (dest.y + dest.height as i32).min(self.height as i32);
+        for y in first_y..last_y {
+            let ty = source.y + ((y - dest.y) as usize * source.height / dest.height) as i32;
+            for x in first_x..last_x {
+                let tx = source.x + ((x - dest.x) as usize * source.width / dest.width) as i32;
+                if tx < 0 || ty < 0 || tx as u32 >= texture.width || ty as u32 >= texture.height {
+                    continue;
+                }
+                let color = texture.sample(tx, ty, AddressMode::Clamp);
+                if color_key != Some(color & 0xFFFFFF) && color >> 24 > 0 {
+                    self.put(y as usize * self.width + x as usize, color, (color >> 24) as u8);
+                }
+            }
+        }
+    }
+
+    // Texto arriba a la izquierda de (x, y), sin más opciones
+    pub fn drawtext(&mut self, text: &str, x: usize, y: usize, font: FontId, scale: Scale, color: u32) {
+        let style = TextStyle { scale, ..TextStyle::new(font, 0.0, color) };
+        self.draw_text(text, x as i32, y as i32, &style);
+    }
+
+    pub fn draw_text(&mut self, text: &str, x: i32, y: i32, style: &TextStyle) -> TextBounds {
+        self.draw_spans(&[Span { text, color: style.color }], x, y, style)
+    }
+
+    // Dibuja varios trozos de texto seguidos, cada uno con su color, y devuelve
+    // el rectángulo que ocupan
+    pub fn draw_spans(&mut self, spans: &[Span], x: i32, y: i32, style: &TextStyle) -> TextBounds {
+        let (glyphs, bounds) = layout(spans, x, y, style);
+        if let Some(shadow) = style.shadow {
+            self.draw_glyphs(&glyphs, style, (2, 2), Some(shadow));
+        }
+        if let Some(outline) = style.outline {
+            for offset in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+                self.draw_glyphs(&glyphs, style, offset, Some(outline));
+            }
+        }
+        self.draw_glyphs(&glyphs, style, (0, 0), None);
+        bounds
+    }
+
+    // Mezcla la cobertura de cada glifo con lo que ya hay en pantalla. Con
+    // `color` se ignora el color de cada glifo, para sombras y bordes.
+    fn draw_glyphs(&mut self, glyphs: &[PlacedGlyph], style: &TextStyle, offset: (i32, i32), color: Option<u32>) {
+        let pen = self.pen();
+        for (glyph, glyph_color) in glyphs {
+            let color = color.unwrap_or(*glyph_color);
+            let (cached, origin_x, origin_y) = self.glyphs.get(style.font, glyph, style.scale);
+            for gy in 0..cached.height {
+                for gx in 0..cached.width {
+                    let coverage = cached.coverage[gy * cached.width + gx];
+                    let px = origin_x + cached.left + gx as i32 + offset.0;
+                    let py = origin_y + cached.top + gy as i32 + offset.1;
+                    if coverage > 0 && px >= 0 && px < self.width as i32 && py >= 0 && py < self.height as i32 {
+                        let index = py as usize * self.width + px as usize;
+                        self.buffer[index] = pen.mix(self.buffer[index], color, coverage);
+                    }
                 }
             }
-    
-            // Actualizar la posición vertical para la próxima línea
-            current_y += line_height;
         }
     }
 
+    // Cantidad de glifos guardados en la caché del texto
+    pub fn cached_glyphs(&self) -> usize {
+        self.glyphs.count()
+    }
+
     // Función para devolver una referencia al buffer de píxeles
     pub fn render_buffer(&self, file_path: &str) {
-        write_bmp_file(file_path, &self.buffer, self.width, self.height)
+        write_bmp_file(file_path, &self.frame, self.width, self.height)
+    }
+}
+
+// Cómo se escribe cada píxel: modo, opacidad y si la capa guarda transparencia
+#[derive(Clone, Copy)]
+struct Pen {
+    mode: BlendMode,
+    opacity: f32,
+    layered: bool,
+}
+
+impl Pen {
+    fn is_opaque(&self) -> bool {
+        self.mode == BlendMode::Alpha && self.opacity >= 1.0
+    }
+
+    // Resultado de dibujar `color` sobre `under`
+    fn mix(&self, under: u32, color: u32, alpha: u8) -> u32 {
+        let alpha = if self.opacity < 1.0 { (alpha as f32 * self.opacity).round() as u8 } else { alpha };
+        let rgb = match self.mode {
+            BlendMode::Alpha if self.layered => return over(under, color, alpha),
+            BlendMode::Alpha if alpha == 255 => color & 0xFFFFFF,
+            BlendMode::Alpha => blend(under, color, alpha),
+            BlendMode::Additive => combine(under, color, |under, over| (under + over * alpha as u32 / 255).min(255)),
+            // Con alpha 0 multiplica por blanco, que no cambia nada
+            BlendMode::Multiply => combine(under, blend(0xFFFFFF, color, alpha), |under, over| under * over / 255),
+        };
+        if self.layered {
+            rgb | (under >> 24).max(alpha as u32) << 24
+        } else {
+            rgb
+        }
     }
 }
 
+// Dibuja sobre un píxel que puede ser transparente: la transparencia del
+// resultado junta las dos
+fn over(under: u32, color: u32, alpha: u8) -> u32 {
+    let (alpha, under_alpha) = (alpha as u32, under >> 24);
+    let below = under_alpha * (255 - alpha) / 255;
+    let total = alpha + below;
+    if total == 0 {
+        return 0;
+    }
+    (total << 24) | combine(under, color, |under, over| (over * alpha + under * below) / total)
+}
+
+// Mezcla dos colores; con alpha 255 queda solo el nuevo
+fn blend(under: u32, over: u32, alpha: u8) -> u32 {
+    let alpha = alpha as u32;
+    combine(under, over, |under, over| (over * alpha + under * (255 - alpha)) / 255)
+}
+
+// Aplica la operación a cada canal de los dos colores
+fn combine(under: u32, over: u32, op: impl Fn(u32, u32) -> u32) -> u32 {
+    let channel = |shift: u32| op((under >> shift) & 0xFF, (over >> shift) & 0xFF) << shift;
+    channel(16) | channel(8) | channel(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,6 +596,92 @@ mod tests {
         fb.point(800, 600); // Out of bounds, should be ignored
     }
 
+    #[test]
+    fn test_shapes_are_clipped() {
+        let mut fb = Framebuffer::new(20, 20);
+        fb.fill_rect(-5, -5, 10, 10, 0xFF0000);
+        assert_eq!(fb.get_point(0, 0), Some(0xFF0000));
+        assert_eq!(fb.get_point(4, 4), Some(0xFF0000));
+        assert_eq!(fb.get_point(5, 5), Some(0x000000));
+
+        // Nada de esto cae dentro, pero tampoco debe fallar
+        fb.clear();
+        fb.line(-50, 30, 100, 30, 0xFFFFFF);
+        fb.fill_circle(-100, -100, 10, 0xFFFFFF);
+        fb.fill_polygon(&[(25, 0), (40, 0), (30, 19)], 0xFFFFFF);
+        fb.line_aa(-10.0, -10.0, -1.0, 50.0, 0xFFFFFF);
+        assert!(fb.buffer.iter().all(|&pixel| pixel == 0));
+
+        fb.line(0, 0, 19, 19, 0x00FF00);
+        assert!((0..20).all(|i| fb.get_point(i, i) == Some(0x00FF00)));
+    }
+
+    #[test]
+    fn test_blend_modes_and_opacity() {
+        let mut fb = Framebuffer::new(4, 1);
+        fb.buffer.fill(0x808080);
+        fb.set_opacity(0.5);
+        fb.fill_rect(0, 0, 1, 1, 0xFFFFFF);
+        fb.set_opacity(1.0);
+        fb.set_blend_mode(BlendMode::Additive);
+        fb.fill_rect(1, 0, 1, 1, 0x900010);
+        fb.set_blend_mode(BlendMode::Multiply);
+        fb.fill_rect(2, 0, 1, 1, 0x00FF80);
+        fb.set_blend_mode(BlendMode::Alpha);
+        fb.blend_point(3, 0, 0x000000, 0);
+
+        assert_eq!(fb.buffer[0], 0xBFBFBF);
+        assert_eq!(fb.buffer[1], 0xFF8090);
+        assert_eq!(fb.buffer[2], 0x008040);
+        assert_eq!(fb.buffer[3], 0x808080);
+    }
+
+    #[test]
+    fn test_layers_are_composited_in_order() {
+        let mut fb = Framebuffer::new(3, 1);
+        fb.buffer.fill(0x0000FF);
+        fb.set_layer(LayerId::Hud);
+        fb.blend_point(0, 0, 0xFF0000, 255);
+        fb.blend_point(1, 0, 0xFF0000, 128);
+        assert_eq!(fb.compose(), &[0xFF0000, 0x80007F, 0x0000FF]);
+
+        fb.set_layer_opacity(LayerId::Hud, 0.0);
+        assert_eq!(fb.compose(), &[0x0000FF; 3]);
+        fb.set_layer_opacity(LayerId::Hud, 1.0);
+
+        // Solo la capa del HUD, sin el mundo de fondo
+        fb.cycle_solo_layer();
+        assert_eq!(fb.cycle_solo_layer(), Some(LayerId::Sprites));
+        fb.cycle_solo_layer();
+        assert_eq!(fb.compose(), &[0xFF0000, 0x800000, 0x000000]);
+
+        // Un cuadro nuevo vacía las capas de arriba
+        fb.begin_frame();
+        fb.solo = None;
+        assert_eq!(fb.compose(), &[0x0000FF; 3]);
+    }
+
+    #[test]
+    fn test_filled_shapes() {
+        let mut fb = Framebuffer::new(40, 40);
+        fb.fill_triangle((0, 0), (39, 0), (0, 39), 0xFFFFFF);
+        assert_eq!(fb.get_point(5, 5), Some(0xFFFFFF));
+        assert_eq!(fb.get_point(35, 35), Some(0x000000));
+
+        fb.clear();
+        fb.fill_circle(20, 20, 10, 0xFFFFFF);
+        assert_eq!(fb.get_point(20, 20), Some(0xFFFFFF));
+        assert_eq!(fb.get_point(20, 30), Some(0xFFFFFF));
+        assert_eq!(fb.get_point(28, 28), Some(0x000000));
+
+        // Un polígono cóncavo en forma de U deja vacío el hueco del medio
+        fb.clear();
+        fb.fill_polygon(&[(0, 0), (10, 0), (10, 20), (30, 20), (30, 0), (40, 0), (40, 30), (0, 30)], 0xFFFFFF);
+        assert_eq!(fb.get_point(5, 5), Some(0xFFFFFF));
+        assert_eq!(fb.get_point(20, 10), Some(0x000000));
+        assert_eq!(fb.get_point(20, 25), Some(0xFFFFFF));
+    }
+
     #[test]
     fn test_get_point() {
         let mut fb = Framebuffer::new(800, 600);
@@ -0,0 +1,336 @@
+use std::f32::consts::PI;
+use std::io;
+use std::path::Path;
+use nalgebra_glm::Vec2;
+use crate::director::ScareDirector;
+use crate::enemy::{Enemy, SPRINT_NOISE, WALK_NOISE};
+use crate::footsteps::{Footsteps, Step};
+use crate::input::InputState;
+use crate::level::LevelConfig;
+use crate::load_maze::load_maze;
+use crate::player::Player;
+use crate::playerController::process_events;
+use crate::replay::{invalid, Replay};
+use crate::rng::Rng;
+use crate::savegame::SaveGame;
+use crate::cast_ray;
+
+// La simulación avanza a paso fijo, independiente de los FPS del render
+pub const TICK_RATE: u32 = 60;
+
+// Salud que quita cada golpe de un enemigo: tres golpes matan
+const HIT_DAMAGE: f32 = 0.34;
+// Cordura que quita cada susto
+const SCARE_FEAR: f32 = 0.15;
+// Cordura por tick: se pierde toda en 10 segundos mirando a un enemigo o en 30
+// en la oscuridad total, y se recupera en 60
+const IN_VIEW_FEAR: f32 = 1.0 / (10 * TICK_RATE) as f32;
+const DARKNESS_FEAR: f32 = 1.0 / (30 * TICK_RATE) as f32;
+const SANITY_REGEN: f32 = 1.0 / (60 * TICK_RATE) as f32;
+// Salud por tick que se pierde sin cordura: toda en 20 segundos
+const MADNESS_DAMAGE: f32 = 1.0 / (20 * TICK_RATE) as f32;
+// Campo de visión con el que la simulación decide si un enemigo está a la
+// vista. Es fijo porque el del jugador es una opción que las grabaciones no
+// guardan; ese solo cambia el dibujo.
+const VIEW_CONE: f32 = PI / 3.0;
+// Cuánto dura el susto en pantalla
+const JUMPSCARE_TICKS: u64 = 2 * TICK_RATE as u64;
+
+// Sucesos de un tick que el bucle principal convierte en sonido o cambios de pantalla
+#[derive(PartialEq, Debug)]
+pub enum GameEvent {
+    Footstep(Step),
+    SoundAt(String, Vec2),
+    Collected(String),
+    Jumpscare(u32), // Variante del susto, para elegir imagen y sonido
+    Died, // El jugador murió y ya terminó el susto
+    ReachedGoal,
+}
+
+// Estado de una partida en un nivel
+pub struct Game {
+    pub maze_file: String,
+    pub maze: Vec<Vec<char>>,
+    pub level: LevelConfig,
+    pub player: Player,
+    pub rng: Rng,
+    pub tick: u64,
+    pub reached_goal: bool,
+    pub show_jumpscare: bool,
+    footsteps: Footsteps,
+    pub triggered: Vec<bool>, // Disparadores de sonido que ya sonaron
+    pub collected: Vec<bool>, // Objetos que ya se recogieron
+    pub steps: u32,
+    pub scares: u32,
+    pub enemies: Vec<Enemy>,
+    pub dead: bool,
+    pub hurt_until_tick: u64, // Hasta este tick los enemigos no vuelven a golpear
+    pub director: ScareDirector,
+    pub scare_variant: u32,
+    jumpscare_end_tick: u64,
+}
+
+impl Game {
+    pub fn new(maze_file: &str, seed: u64) -> Game {
+        let maze = load_maze(maze_file);
+        let block_size = 50; // Tamaño de cada bloque en píxeles
+
+        // Inicializar el jugador en la posición del carácter 'p'
+        let mut player_pos = (0.0, 0.0);
+        for (y, row) in maze.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                if cell == 'p' {
+                    player_pos = (x as f32 * block_size as f32, y as f32 * block_size as f32);
+                }
+            }
+        }
+
+        let level = LevelConfig::load(maze_file);
+        Game {
+            maze_file: maze_file.to_string(),
+            maze,
+            triggered: vec![false; level.triggers.len()],
+            collected: vec![false; level.collectibles.len()],
+            steps: 0,
+            scares: 0,
+            enemies: level.enemies.iter().map(|spawn| Enemy::new(spawn, block_size)).collect(),
+            dead: false,
+            hurt_until_tick: 0,
+            level,
+            player: Player::new(player_pos.0, player_pos.1, PI / 3.0, PI / 3.0),
+            rng: Rng::new(seed),
+            tick: 0,
+            reached_goal: false,
+            show_jumpscare: false,
+            footsteps: Footsteps::new(),
+            director: ScareDirector::new(),
+            scare_variant: 0,
+            jumpscare_end_tick: 0,
+        }
+    }
+
+    // Oscuridad donde está el jugador
+    pub fn darkness(&self) -> f32 {
+        let block_size = 50;
+        let col = self.player.pos.x as usize / block_size;
+        let row = self.player.pos.y as usize / block_size;
+        self.level.darkness_at(col, row)
+    }
+
+    // Distancia al enemigo más cercano y si alguno está a la vista: dentro de
+    // VIEW_CONE y sin una pared más cerca en esa dirección
+    fn threats(&self, block_size: usize) -> (f32, bool) {
+        let mut nearest = f32::INFINITY;
+        let mut in_view = false;
+        for enemy in &self.enemies {
+            let delta = enemy.pos - self.player.pos;
+            let distance = delta.norm();
+            nearest = nearest.min(distance);
+            let angle = delta.y.atan2(delta.x);
+            let offset = (angle - self.player.a + PI).rem_euclid(2.0 * PI) - PI;
+            if offset.abs() <= VIEW_CONE / 2.0 {
+                let wall = cast_ray(None, &self.maze, &self.player, angle, block_size).intersect.distance;
+                in_view |= wall >= distance;
+            }
+        }
+        (nearest, in_view)
+    }
+
+    // Avanza la simulación un tick
+    pub fn update(&mut self, input: &InputState) -> Vec<GameEvent> {
+        let block_size = 50;
+        let mut events = Vec::new();
+        self.tick += 1;
+
+        // Muerto: el susto se muestra completo antes de terminar la partida
+        if self.dead {
+            if self.tick == self.jumpscare_end_tick {
+                events.push(GameEvent::Died);
+            }
+            return events;
+        }
+
+        let movement = process_events(input, &mut self.player, &self.maze);
+        let col = self.player.pos.x as usize / block_size;
+        let row = self.player.pos.y as usize / block_size;
+        let surface = self.level.surface_at(col, row);
+        let mut noise = 0.0;
+        if let Some(step) = self.footsteps.update(movement.distance, movement.sprinting, surface, &mut self.rng) {
+            self.steps += 1;
+            noise = if movement.sprinting { SPRINT_NOISE } else { WALK_NOISE };
+            events.push(GameEvent::Footstep(step));
+        }
+
+        let mut touched = false;
+        for (enemy, spawn) in self.enemies.iter_mut().zip(&self.level.enemies) {
+            touched |= enemy.update(spawn, &self.maze, self.player.pos, noise, block_size);
+        }
+
+        for (index, trigger) in self.level.triggers.iter().enumerate() {
+            if !self.triggered[index] && trigger.cell == (col, row) {
+                self.triggered[index] = true;
+                events.push(GameEvent::SoundAt(trigger.file.clone(), cell_center(trigger.cell, block_size)));
+            }
+        }
+
+        for (index, item) in self.level.collectibles.iter().enumerate() {
+            if !self.collected[index] && item.cell == (col, row) {
+                self.collected[index] = true;
+                events.push(GameEvent::Collected(item.name.clone()));
+            }
+        }
+
+        let (nearest, in_view) = self.threats(block_size);
+        // Después de un golpe hay un momento para escapar antes del siguiente
+        let hit = touched && self.tick >= self.hurt_until_tick;
+        if hit {
+            self.player.health = (self.player.health - HIT_DAMAGE).max(0.0);
+            self.player.sanity = (self.player.sanity - SCARE_FEAR).max(0.0);
+            self.hurt_until_tick = self.tick + 2 * TICK_RATE as u64;
+            // El susto de un golpe es siempre el del enemigo
+            self.start_jumpscare(0);
+            events.push(GameEvent::Jumpscare(0));
+        } else if let Some(variant) = self.director.update(self.tick, nearest, in_view, &mut self.rng) {
+            self.start_jumpscare(variant);
+            self.scares += 1;
+            self.player.sanity = (self.player.sanity - SCARE_FEAR).max(0.0);
+            events.push(GameEvent::Jumpscare(variant));
+        } else if self.tick > self.jumpscare_end_tick {
+            self.show_jumpscare = false;
+        }
+
+        // La cordura baja a la vista de un enemigo y en la oscuridad, y se
+        // recupera despacio en calma. Sin cordura, el miedo también hace daño.
+        let mut drain = self.darkness() * DARKNESS_FEAR;
+        if in_view {
+            drain += IN_VIEW_FEAR;
+        }
+        self.player.sanity = if drain > 0.0 {
+            (self.player.sanity - drain).max(0.0)
+        } else {
+            (self.player.sanity + SANITY_REGEN).min(1.0)
+        };
+        if self.player.sanity <= 0.0 {
+            self.player.health = (self.player.health - MADNESS_DAMAGE).max(0.0);
+        }
+
+        if self.player.health <= 0.0 {
+            self.dead = true;
+            if !hit {
+                self.start_jumpscare(0);
+                events.push(GameEvent::Jumpscare(0));
+            }
+            return events;
+        }
+
+        let cast_result = cast_ray(None, &self.maze, &self.player, self.player.a, block_size);
+        if cast_result.reached_goal && !self.reached_goal {
+            self.reached_goal = true;
+            events.push(GameEvent::ReachedGoal);
+        }
+
+        events
+    }
+
+    fn start_jumpscare(&mut self, variant: u32) {
+        self.show_jumpscare = true;
+        self.scare_variant = variant;
+        self.jumpscare_end_tick = self.tick + JUMPSCARE_TICKS;
+    }
+
+    // Opacidad del susto en pantalla: aparece en un cuarto de segundo
+    pub fn jumpscare_fade(&self) -> f32 {
+        let shown = (self.tick + JUMPSCARE_TICKS).saturating_sub(self.jumpscare_end_tick);
+        (shown as f32 / (TICK_RATE / 4) as f32).min(1.0)
+    }
+
+    pub fn to_save(&self) -> SaveGame {
+        SaveGame {
+            level: self.maze_file.clone(),
+            pos: self.player.pos,
+            angle: self.player.a,
+            stamina: self.player.stamina,
+            health: self.player.health,
+            sanity: self.player.sanity,
+            triggered: self.triggered.clone(),
+            collected: self.collected.clone(),
+            steps: self.steps,
+            scares: self.scares,
+            enemies: self.enemies.clone(),
+            dead: self.dead,
+            hurt_until_tick: self.hurt_until_tick,
+            tick: self.tick,
+            rng_state: self.rng.state(),
+            tension: self.director.tension,
+            last_scare_tick: self.director.last_scare_tick,
+            scare_variant: self.scare_variant,
+            jumpscare_end_tick: self.jumpscare_end_tick,
+            step_distance: self.footsteps.distance,
+            left_foot: self.footsteps.left,
+            still_ticks: self.footsteps.still_ticks,
+        }
+    }
+
+    // Continúa una partida guardada. Falla si el nivel ya no existe.
+    pub fn from_save(save: &SaveGame) -> io::Result<Game> {
+        if !Path::new(&save.level).exists() {
+            return Err(invalid(&format!("el nivel {} ya no existe", save.level)));
+        }
+        let mut game = Game::new(&save.level, 0);
+        game.player.pos = save.pos;
+        game.player.a = save.angle;
+        game.player.stamina = save.stamina;
+        game.player.health = save.health;
+        game.player.sanity = save.sanity;
+        // Si el nivel cambió y tiene otros disparadores, los nuevos quedan sin sonar
+        for (triggered, saved) in game.triggered.iter_mut().zip(&save.triggered) {
+            *triggered = *saved;
+        }
+        for (collected, saved) in game.collected.iter_mut().zip(&save.collected) {
+            *collected = *saved;
+        }
+        for ((enemy, saved), spawn) in game.enemies.iter_mut().zip(&save.enemies).zip(&game.level.enemies) {
+            if saved.patrol_index < spawn.patrol.len() {
+                *enemy = saved.clone();
+            }
+        }
+        game.dead = save.dead;
+        game.hurt_until_tick = save.hurt_until_tick;
+        game.steps = save.steps;
+        game.scares = save.scares;
+        game.tick = save.tick;
+        game.rng = Rng::from_state(save.rng_state);
+        game.director.tension = save.tension;
+        game.director.last_scare_tick = save.last_scare_tick;
+        game.scare_variant = save.scare_variant;
+        game.jumpscare_end_tick = save.jumpscare_end_tick;
+        game.show_jumpscare = game.tick <= game.jumpscare_end_tick && game.jumpscare_end_tick > 0;
+        game.footsteps.distance = save.step_distance;
+        game.footsteps.left = save.left_foot;
+        game.footsteps.still_ticks = save.still_ticks;
+        Ok(game)
+    }
+
+    // Ejecuta una repetición completa sin ventana y devuelve el estado final
+    pub fn run_replay(replay: &Replay) -> Game {
+        let mut game = Game::new(&replay.level, replay.seed);
+        game.player.sensitivity = replay.sensitivity;
+        let mut input = InputState::new();
+        for frame in &replay.frames {
+            frame.apply(&mut input);
+            let events = game.update(&input);
+            if game.reached_goal || events.contains(&GameEvent::Died) {
+                break;
+            }
+        }
+        game
+    }
+}
+
+// Centro de una celda del laberinto en píxeles
+pub fn cell_center(cell: (usize, usize), block_size: usize) -> Vec2 {
+    Vec2::new(
+        (cell.0 * block_size + block_size / 2) as f32,
+        (cell.1 * block_size + block_size / 2) as f32,
+    )
+}
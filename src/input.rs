@@ -0,0 +1,99 @@
+use minifb::{Key, MouseButton, MouseMode, Window};
+
+const LETTERS: [Key; 26] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+];
+
+// Estado de la entrada de un cuadro. Guarda las teclas del cuadro actual y las del
+// anterior para distinguir entre tecla recién presionada, recién soltada o mantenida.
+pub struct InputState {
+    current: Vec<Key>,
+    previous: Vec<Key>,
+    pub mouse_pos: Option<(f32, f32)>,
+    mouse_down: bool,
+    was_mouse_down: bool,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        InputState {
+            current: Vec::new(),
+            previous: Vec::new(),
+            mouse_pos: None,
+            mouse_down: false,
+            was_mouse_down: false,
+        }
+    }
+
+    // Lee el estado de la ventana; se llama una vez por cuadro
+    pub fn update(&mut self, window: &Window) {
+        self.update_with(window.get_keys(), window.get_mouse_pos(MouseMode::Clamp));
+        self.set_mouse_down(window.get_mouse_down(MouseButton::Left));
+    }
+
+    pub fn set_mouse_down(&mut self, down: bool) {
+        self.was_mouse_down = self.mouse_down;
+        self.mouse_down = down;
+    }
+
+    // Letra que se presionó en este cuadro, para escribir texto
+    pub fn typed_letter(&self) -> Option<char> {
+        LETTERS.iter().position(|key| self.is_pressed(*key)).map(|index| (b'A' + index as u8) as char)
+    }
+
+    // El botón izquierdo se soltó en este cuadro, como un clic de menú
+    pub fn is_clicked(&self) -> bool {
+        !self.mouse_down && self.was_mouse_down
+    }
+
+    // Avanza un cuadro con las teclas y el mouse dados
+    pub fn update_with(&mut self, keys: Vec<Key>, mouse_pos: Option<(f32, f32)>) {
+        self.previous = std::mem::replace(&mut self.current, keys);
+        self.mouse_pos = mouse_pos;
+    }
+
+    // La tecla está abajo en este cuadro
+    pub fn is_held(&self, key: Key) -> bool {
+        self.current.contains(&key)
+    }
+
+    // La tecla bajó en este cuadro (flanco de subida)
+    pub fn is_pressed(&self, key: Key) -> bool {
+        self.current.contains(&key) && !self.previous.contains(&key)
+    }
+
+    // La tecla se soltó en este cuadro (flanco de bajada)
+    pub fn is_released(&self, key: Key) -> bool {
+        !self.current.contains(&key) && self.previous.contains(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pressed_only_on_first_frame() {
+        let mut input = InputState::new();
+        input.update_with(vec![Key::M], None);
+        assert!(input.is_pressed(Key::M));
+        assert!(input.is_held(Key::M));
+
+        input.update_with(vec![Key::M], None);
+        assert!(!input.is_pressed(Key::M));
+        assert!(input.is_held(Key::M));
+    }
+
+    #[test]
+    fn test_released() {
+        let mut input = InputState::new();
+        input.update_with(vec![Key::P], None);
+        input.update_with(vec![], None);
+        assert!(input.is_released(Key::P));
+        assert!(!input.is_held(Key::P));
+
+        input.update_with(vec![], None);
+        assert!(!input.is_released(Key::P));
+    }
+}
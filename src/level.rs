@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::enemy::EnemySpawn;
+use crate::postprocess::PostEffect;
+
+// Tipo de piso de una celda; cambia el sonido de los pasos
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Surface {
+    Piedra,
+    Madera,
+    Agua,
+    Tierra,
+}
+
+impl Surface {
+    pub const ALL: [Surface; 4] = [Surface::Piedra, Surface::Madera, Surface::Agua, Surface::Tierra];
+
+    pub fn parse(name: &str) -> Option<Surface> {
+        match name {
+            "piedra" => Some(Surface::Piedra),
+            "madera" => Some(Surface::Madera),
+            "agua" => Some(Surface::Agua),
+            "tierra" => Some(Surface::Tierra),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Surface::Piedra => "piedra",
+            Surface::Madera => "madera",
+            Surface::Agua => "agua",
+            Surface::Tierra => "tierra",
+        }
+    }
+}
+
+// Configuración opcional de un nivel. Se lee de un archivo con el mismo nombre
+// del laberinto y extensión .cfg (maze1.txt -> maze1.cfg), con líneas como:
+//
+//   musica = music.mp3
+//   piso = piedra
+//   superficie 3,1 = madera
+//   superficie 1,5 4,7 = agua
+//   emisor 7,3 = gotas.mp3
+//   disparador 10,5 = screamer.mp3
+//   objeto 4,3 = llave
+//   enemigo 7,1 13,1 13,5 = 2 4
+//   oscuro 9,1 11,5 = 0.8
+//   efecto grano = 0.1
+//   efecto color = sepia.png
+//
+// Las coordenadas son columna,fila de los caracteres del laberinto; con dos
+// coordenadas se cubre el rectángulo entre ellas. Un emisor suena en bucle
+// desde su celda y un disparador suena una vez cuando el jugador la pisa. Un
+// objeto se recoge al pasar por su celda y suma puntos. Un enemigo aparece en
+// la primera celda y patrulla por todas en orden; los números son su velocidad
+// al patrullar y al perseguir, en píxeles por tick. Las zonas oscuras, de 0.0 a
+// 1.0, se ven con niebla y le quitan cordura al jugador. Los efectos de pantalla
+// son vineta, desaturar, grano, lineas y aberracion con su intensidad, y color
+// con una tabla de color (ver postprocess::Lut).
+pub struct LevelConfig {
+    pub music: String,
+    pub floor: Surface,
+    surfaces: HashMap<(usize, usize), Surface>,
+    darkness: HashMap<(usize, usize), f32>,
+    pub emitters: Vec<SoundSpot>,
+    pub triggers: Vec<SoundSpot>,
+    pub collectibles: Vec<Collectible>,
+    pub enemies: Vec<EnemySpawn>,
+    pub effects: Vec<PostEffect>,
+    pub color_grade: Option<String>, // Imagen de la tabla de color
+}
+
+// Sonido ubicado en una celda del laberinto
+pub struct SoundSpot {
+    pub cell: (usize, usize),
+    pub file: String,
+}
+
+// Objeto que el jugador puede recoger
+pub struct Collectible {
+    pub cell: (usize, usize),
+    pub name: String,
+}
+
+impl LevelConfig {
+    pub fn load(maze_file: &str) -> LevelConfig {
+        let config_path = Path::new(maze_file).with_extension("cfg");
+        match fs::read_to_string(&config_path) {
+            Ok(text) => LevelConfig::parse(&text),
+            Err(_) => LevelConfig::parse(""),
+        }
+    }
+
+    pub fn parse(text: &str) -> LevelConfig {
+        let mut config = LevelConfig {
+            music: "music.mp3".to_string(),
+            floor: Surface::Piedra,
+            surfaces: HashMap::new(),
+            darkness: HashMap::new(),
+            emitters: Vec::new(),
+            triggers: Vec::new(),
+            collectibles: Vec::new(),
+            enemies: Vec::new(),
+            effects: Vec::new(),
+            color_grade: None,
+        };
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                eprintln!("Línea {} ignorada en la configuración del nivel: {}", number + 1, line);
+                continue;
+            };
+            let mut key_parts = key.split_whitespace();
+            let value = value.trim();
+
+            match key_parts.next() {
+                Some("musica") => config.music = value.to_string(),
+                Some("piso") => match Surface::parse(value) {
+                    Some(surface) => config.floor = surface,
+                    None => eprintln!("Superficie desconocida en la línea {}: {}", number + 1, value),
+                },
+                Some("superficie") => {
+                    let cells: Vec<(usize, usize)> = key_parts.filter_map(parse_cell).collect();
+                    match (Surface::parse(value), cell_area(&cells)) {
+                        (Some(surface), Some(area)) => {
+                            for cell in area {
+                                config.surfaces.insert(cell, surface);
+                            }
+                        }
+                        _ => eprintln!("Superficie inválida en la línea {}: {}", number + 1, line),
+                    }
+                }
+                Some("oscuro") => {
+                    let cells: Vec<(usize, usize)> = key_parts.filter_map(parse_cell).collect();
+                    match (value.parse::<f32>(), cell_area(&cells)) {
+                        (Ok(amount), Some(area)) => {
+                            for cell in area {
+                                config.darkness.insert(cell, amount.clamp(0.0, 1.0));
+                            }
+                        }
+                        _ => eprintln!("Zona oscura inválida en la línea {}: {}", number + 1, line),
+                    }
+                }
+                Some(kind @ ("emisor" | "disparador")) => {
+                    let Some(cell) = key_parts.next().and_then(parse_cell) else {
+                        eprintln!("Celda inválida en la línea {}: {}", number + 1, line);
+                        continue;
+                    };
+                    let spot = SoundSpot { cell, file: value.to_string() };
+                    if kind == "emisor" {
+                        config.emitters.push(spot);
+                    } else {
+                        config.triggers.push(spot);
+                    }
+                }
+                Some("objeto") => match key_parts.next().and_then(parse_cell) {
+                    Some(cell) => config.collectibles.push(Collectible { cell, name: value.to_string() }),
+                    None => eprintln!("Celda inválida en la línea {}: {}", number + 1, line),
+                },
+                Some("enemigo") => {
+                    let patrol: Vec<(usize, usize)> = key_parts.filter_map(parse_cell).collect();
+                    let speeds: Vec<f32> = value.split_whitespace().filter_map(|speed| speed.parse().ok()).collect();
+                    match speeds.as_slice() {
+                        &[speed, chase_speed] if !patrol.is_empty() => {
+                            config.enemies.push(EnemySpawn { patrol, speed, chase_speed });
+                        }
+                        _ => eprintln!("Enemigo inválido en la línea {}: {}", number + 1, line),
+                    }
+                }
+                Some("efecto") => match (key_parts.next(), value) {
+                    (Some("color"), file) => config.color_grade = Some(file.to_string()),
+                    (Some(name), amount) => match PostEffect::parse(name, amount) {
+                        Some(effect) => config.effects.push(effect),
+                        None => eprintln!("Efecto inválido en la línea {}: {}", number + 1, line),
+                    },
+                    (None, _) => eprintln!("Falta el nombre del efecto en la línea {}: {}", number + 1, line),
+                },
+                _ => eprintln!("Clave desconocida en la línea {}: {}", number + 1, key.trim()),
+            }
+        }
+
+        config
+    }
+
+    pub fn surface_at(&self, col: usize, row: usize) -> Surface {
+        *self.surfaces.get(&(col, row)).unwrap_or(&self.floor)
+    }
+
+    // Oscuridad de una celda, 0.0 si está iluminada
+    pub fn darkness_at(&self, col: usize, row: usize) -> f32 {
+        *self.darkness.get(&(col, row)).unwrap_or(&0.0)
+    }
+}
+
+// Celdas que cubre una o dos coordenadas; con dos, el rectángulo entre ellas
+fn cell_area(cells: &[(usize, usize)]) -> Option<Vec<(usize, usize)>> {
+    let (from, to) = match cells {
+        [cell] => (*cell, *cell),
+        [from, to] => (*from, *to),
+        _ => return None,
+    };
+    let mut area = Vec::new();
+    for col in from.0.min(to.0)..=from.0.max(to.0) {
+        for row in from.1.min(to.1)..=from.1.max(to.1) {
+            area.push((col, row));
+        }
+    }
+    Some(area)
+}
+
+// Convierte "columna,fila" en una celda
+fn parse_cell(text: &str) -> Option<(usize, usize)> {
+    let (col, row) = text.split_once(',')?;
+    Some((col.trim().parse().ok()?, row.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_surfaces() {
+        let config = LevelConfig::parse("musica = nivel.mp3\npiso = tierra\nsuperficie 3,1 = madera\nsuperficie 1,5 2,6 = agua\noscuro 4,1 5,2 = 0.6\nefecto grano = 0.2\nefecto brillo = 2\nefecto color = sepia.png\n");
+        assert_eq!(config.music, "nivel.mp3");
+        assert_eq!(config.floor, Surface::Tierra);
+        assert_eq!(config.surface_at(3, 1), Surface::Madera);
+        assert_eq!(config.surface_at(2, 6), Surface::Agua);
+        assert_eq!(config.surface_at(3, 6), Surface::Tierra);
+        assert_eq!(config.darkness_at(5, 2), 0.6);
+        assert_eq!(config.darkness_at(3, 1), 0.0);
+        assert_eq!(config.effects.len(), 1);
+        assert!(matches!(config.effects[0], PostEffect::Grain(amount) if amount == 0.2));
+        assert_eq!(config.color_grade.as_deref(), Some("sepia.png"));
+    }
+
+    #[test]
+    fn test_parse_sound_spots() {
+        let config = LevelConfig::parse("emisor 7,3 = gotas.mp3\ndisparador 10,5 = screamer.mp3\ndisparador x = a.mp3\nobjeto 4,3 = llave\nenemigo 1,1 5,1 = 2 4.5\nenemigo 2,2 = rápido\n");
+        assert_eq!(config.emitters.len(), 1);
+        assert_eq!(config.emitters[0].cell, (7, 3));
+        assert_eq!(config.triggers.len(), 1);
+        assert_eq!(config.triggers[0].file, "screamer.mp3");
+        assert_eq!(config.collectibles[0].cell, (4, 3));
+        assert_eq!(config.collectibles[0].name, "llave");
+        assert_eq!(config.enemies.len(), 1);
+        assert_eq!(config.enemies[0].patrol, [(1, 1), (5, 1)]);
+        assert_eq!(config.enemies[0].chase_speed, 4.5);
+    }
+
+    #[test]
+    fn test_missing_config_uses_defaults() {
+        let config = LevelConfig::load("no_existe.txt");
+        assert_eq!(config.music, "music.mp3");
+        assert_eq!(config.floor, Surface::Piedra);
+    }
+}
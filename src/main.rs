@@ -4,26 +4,62 @@ mod bmp;
 mod player;
 mod playerController;
 mod textures;
+mod input;
+mod rng;
+mod replay;
+mod game;
+mod audio;
+mod level;
+mod footsteps;
+mod positional;
+mod music;
+mod settings;
+mod scene;
+mod playing;
+mod menu;
+mod campaign;
+mod savegame;
+mod score;
+mod navigation;
+mod enemy;
+mod director;
+mod effects;
+mod fonts;
+mod text;
+mod postprocess;
+mod assets;
 
 use load_maze::load_maze;
-use textures::Texture;
-use framebuffer::Framebuffer;
+use textures::{AddressMode, Filter, Texture};
+use framebuffer::{Framebuffer, LayerId, Rect};
+use fonts::FontId;
 use player::Player;
-use minifb::{Window, WindowOptions, Key};
-use std::f32::consts::PI;
+use minifb::{Window, WindowOptions, Key, ScaleMode};
 use nalgebra_glm::{Vec2};
 use std::time::{Duration, Instant};
-use playerController::process_events;
+use input::InputState;
+use replay::Replay;
+use game::{Game, cell_center};
+use director::{SCARE_IMAGES, SCARE_SOUNDS, SCARE_VARIANTS};
+use assets::{missing_assets, CORNER_TEXTURE, SCARE_TEXTURE_SIZE, WALL_TEXTURE, WALL_TEXTURE_SIZE};
+use effects::ScreenEffects;
+use audio::{AudioBackend, Channel};
+use level::{LevelConfig, Surface};
+use music::MusicController;
+use settings::Settings;
+use scene::{App, Scene, SceneStack};
+use playing::Playing;
+use menu::Menu;
+use campaign::{Campaign, Progress, PROGRESS_FILE};
+use score::{Leaderboard, RECORDS_FILE};
+use std::path::Path;
 use image::GenericImageView;
 use std::collections::HashMap;
 use image::{RgbImage, RgbaImage};
-use rodio::{Decoder, OutputStream, Sink};
-use std::fs::File;
-use std::io::BufReader;
 use rusttype::Scale;
 use once_cell::sync::Lazy;
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::sync::Arc;
+use std::f32::consts::PI;
 
 pub struct Intersect {
     pub distance: f32,
@@ -35,151 +71,253 @@ pub struct CastRayResult {
     pub reached_goal: bool,
 }
 
-static wall: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture:: new("patterned_plaster_wall_diff_4k.jpg")));
-static corner: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture:: new("patterned_plaster_wall_disp_4k.png")));
-static jumpscare: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture:: new("creepy.png")));
-
-fn cell_to_texture_color(cell: char, tx: u32, ty: u32) -> u32 {
-    let default_color = 0x0000000;
+static wall: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::load_or_placeholder(WALL_TEXTURE, WALL_TEXTURE_SIZE)));
+static corner: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::load_or_placeholder(CORNER_TEXTURE, WALL_TEXTURE_SIZE)));
+static jumpscare: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::load_or_placeholder(SCARE_IMAGES[0], SCARE_TEXTURE_SIZE)));
+// Una imagen por variante de susto: cada variante toma una de las imágenes que
+// existen y la espeja o tiñe. La primera es la del enemigo tal cual.
+static SCARE_TEXTURES: Lazy<Vec<Arc<Texture>>> = Lazy::new(|| {
+    let mut images = vec![Arc::clone(&jumpscare)];
+    images.extend(
+        SCARE_IMAGES[1..]
+            .iter()
+            .filter(|file| Path::new(file).exists())
+            .map(|file| Arc::new(Texture::load_or_placeholder(file, SCARE_TEXTURE_SIZE))),
+    );
+    SCARE_VARIANTS
+        .iter()
+        .enumerate()
+        .map(|(index, variant)| {
+            let image = &images[index % images.len()];
+            if variant.flip || variant.tint != 0xFFFFFF {
+                Arc::new(image.variant(variant.flip, variant.tint))
+            } else {
+                Arc::clone(image)
+            }
+        })
+        .collect()
+});
 
+// Textura de cada tipo de pared; las demás celdas tienen un color fijo
+fn cell_texture(cell: char) -> Option<&'static Texture> {
     match cell {
-        '+' => corner.get_pixel_color(tx, ty),
-        '-' => wall.get_pixel_color(tx, ty),
-        '|' => wall.get_pixel_color(tx, ty),
-        'g' => 0xFF0000, // Color rojo
-        _ => default_color,
-    }
-}
-
-fn draw_wall_horizontal(framebuffer: &mut Framebuffer, xo: usize, yo: usize, length: usize) {
-    framebuffer.set_current_color(0x000000); // Color negro para las paredes
-    for x in xo..xo + length {
-        framebuffer.point(x, yo);
+        '+' => Some(&**corner),
+        '-' | '|' => Some(&**wall),
+        _ => None,
     }
 }
 
-fn draw_wall_vertical(framebuffer: &mut Framebuffer, xo: usize, yo: usize, length: usize) {
-    framebuffer.set_current_color(0x000000); // Color negro para las paredes
-    for y in yo..yo + length {
-        framebuffer.point(xo, y);
+fn cell_color(cell: char) -> u32 {
+    match cell {
+        'g' => 0xFF0000, // Color rojo
+        _ => 0x000000,
     }
 }
 
 fn draw_cell(framebuffer: &mut Framebuffer, xo: usize, yo: usize, cell: char, block_size:usize) {
-    //let block_size = 50; // Tamaño de cada bloque en píxeles
+    let (x, y) = (xo as i32, yo as i32);
 
+    // Las paredes son líneas negras en el borde de arriba o de la izquierda
     match cell {
         '+' => {
-            draw_wall_horizontal(framebuffer, xo, yo, block_size);
-            draw_wall_vertical(framebuffer, xo, yo, block_size);
-        }
-        '-' => draw_wall_horizontal(framebuffer, xo, yo, block_size),
-        '|' => draw_wall_vertical(framebuffer, xo, yo, block_size),
-        'p' => {
-            framebuffer.set_current_color(0x00FF00); // Color verde para la meta
-            for y in yo..yo + block_size {
-                for x in xo..xo + block_size {
-                    framebuffer.point(x, y);
-                }
-            }
-        }
-        'g' => {
-            framebuffer.set_current_color(0xFF0000); // Color rojo para la meta
-            for y in yo..yo + block_size {
-                for x in xo..xo + block_size {
-                    framebuffer.point(x, y);
-                }
-            }
-        }
-        ' ' => {
-            framebuffer.set_current_color(0xFFFFFF); // Color blanco para el camino
-            for y in yo..yo + block_size {
-                for x in xo..xo + block_size {
-                    framebuffer.point(x, y);
-                }
-            }
+            framebuffer.fill_rect(x, y, block_size, 1, 0x000000);
+            framebuffer.fill_rect(x, y, 1, block_size, 0x000000);
         }
+        '-' => framebuffer.fill_rect(x, y, block_size, 1, 0x000000),
+        '|' => framebuffer.fill_rect(x, y, 1, block_size, 0x000000),
+        'p' => framebuffer.fill_rect(x, y, block_size, block_size, 0x00FF00), // Verde
+        'g' => framebuffer.fill_rect(x, y, block_size, block_size, 0xFF0000), // Rojo para la meta
+        ' ' => framebuffer.fill_rect(x, y, block_size, block_size, 0xFFFFFF), // Blanco para el camino
         _ => {}
     }
 }
 
-fn render2D(framebuffer: &mut Framebuffer, player: &Player, maze_file: &str) {
+// Rombo amarillo centrado en (x, y) que marca un objeto
+fn draw_collectible(framebuffer: &mut Framebuffer, x: i32, y: i32, radius: i32) {
+    framebuffer.fill_polygon(&[(x, y - radius), (x + radius + 1, y), (x, y + radius + 1), (x - radius, y)], 0xFFCC00);
+}
+
+// Punto morado centrado en (x, y) que marca un enemigo
+fn draw_enemy_marker(framebuffer: &mut Framebuffer, x: i32, y: i32, radius: i32) {
+    framebuffer.fill_circle(x, y, radius, 0x9900CC);
+}
+
+fn render2D(framebuffer: &mut Framebuffer, player: &Player, maze_file: &str, collectibles: &[(usize, usize)], enemies: &[Vec2]) {
     let maze = load_maze(maze_file);
     let block_size = 50; // Tamaño de cada bloque en píxeles
 
+    // El laberinto se escala para entrar en la pantalla, centrado, sea cual sea su tamaño
+    let maze_width = (maze[0].len() * block_size) as f32;
+    let maze_height = (maze.len() * block_size) as f32;
+    let scale = (framebuffer.width as f32 / maze_width).min(framebuffer.height as f32 / maze_height);
+    let offset_x = (framebuffer.width as f32 - maze_width * scale) / 2.0;
+    let offset_y = (framebuffer.height as f32 - maze_height * scale) / 2.0;
+    let to_screen = |pos: Vec2| (offset_x + pos.x * scale, offset_y + pos.y * scale);
+    let cell_size = (block_size as f32 * scale).ceil() as usize;
+
     for (row, line) in maze.iter().enumerate() {
         for (col, &cell) in line.iter().enumerate() {
-            draw_cell(framebuffer, col * block_size, row * block_size, cell, 50);
+            let (x, y) = to_screen(Vec2::new((col * block_size) as f32, (row * block_size) as f32));
+            draw_cell(framebuffer, x as usize, y as usize, cell, cell_size);
         }
     }
 
+    for &cell in collectibles {
+        let (x, y) = to_screen(cell_center(cell, block_size));
+        draw_collectible(framebuffer, x as i32, y as i32, (8.0 * scale).max(1.0) as i32);
+    }
+
+    for &enemy in enemies {
+        let (x, y) = to_screen(enemy);
+        draw_enemy_marker(framebuffer, x as i32, y as i32, (10.0 * scale).max(1.0) as i32);
+    }
+
     // Cast a ray from the player's position
     let num_rays = 5;
+    let (start_x, start_y) = to_screen(player.pos);
     for i in 0..num_rays {
         let current_ray = i as f32 / num_rays as f32;
         let a = player.a - (player.fov / 2.0) + (player.fov * current_ray);
-        cast_ray(framebuffer, &maze, &player, a, block_size, true);
+        let distance = cast_ray(None, &maze, &player, a, block_size).intersect.distance;
+        let (end_x, end_y) = to_screen(player.pos + Vec2::new(a.cos(), a.sin()) * distance);
+        framebuffer.line_aa(start_x, start_y, end_x, end_y, 0x0000FF); // Color azul para el rayo
     }
 }
 
-fn render3D(framebuffer: &mut Framebuffer, player: &Player, maze_file: &str) {
+// Distancia a la que la niebla más densa ya tapa por completo las paredes
+const FOG_DISTANCE: f32 = 500.0;
+
+// Oscurece un color según la distancia; con fog en 0.0 no cambia
+fn apply_fog(color: u32, distance: f32, fog: f32) -> u32 {
+    let amount = 1.0 - (distance / FOG_DISTANCE).min(1.0) * fog;
+    let r = (((color >> 16) & 0xFF) as f32 * amount) as u32;
+    let g = (((color >> 8) & 0xFF) as f32 * amount) as u32;
+    let b = ((color & 0xFF) as f32 * amount) as u32;
+    (r << 16) | (g << 8) | b
+}
+
+fn render3D(framebuffer: &mut Framebuffer, player: &Player, maze_file: &str, settings: &Settings, enemies: &[Vec2], effects: &ScreenEffects) {
     let maze = load_maze(maze_file);
     let block_size = 50; // Tamaño de cada bloque en píxeles
     let num_rays = framebuffer.width;
 
     let hw = framebuffer.width as f32 / 2.0;
-    let hh = framebuffer.height as f32 / 2.0;
+    // El temblor mueve el horizonte y gira un poco la vista
+    let hh = framebuffer.height as f32 / 2.0 + effects.shake.1;
+    let view_angle = player.a + effects.shake.0 * player.fov / framebuffer.width as f32;
+    // En las zonas oscuras la niebla es al menos tan densa como la oscuridad
+    let fog = settings.fog.max(effects.darkness);
 
     // Color blanco para el fondo
     framebuffer.set_current_color(0xFFFFFF);
     framebuffer.clear();
 
-    for i in 0..num_rays {
+    // Con menos resolución, cada rayo pinta varias columnas
+    let column_width = (1.0 / settings.resolution_scale).round().max(1.0) as usize;
+
+    // Distancia a la pared de cada columna, para tapar a los enemigos que estén detrás
+    let mut zbuffer = vec![f32::INFINITY; framebuffer.width];
+
+    for i in (0..num_rays).step_by(column_width) {
         let current_ray = i as f32 / num_rays as f32;
-        let a = player.a - (player.fov / 2.0) + (player.fov * current_ray);
-        let intersect = cast_ray(framebuffer, &maze, &player, a, block_size, false);
+        let a = view_angle - (player.fov / 2.0) + (player.fov * current_ray);
+        let intersect = cast_ray(None, &maze, &player, a, block_size);
+        let columns = i..(i + column_width).min(framebuffer.width);
 
         let distance_to_wall = intersect.intersect.distance;
         let distance_to_projection_plane = (framebuffer.width as f32 / 2.0) / (player.fov / 2.0).tan();
         
         if distance_to_wall > 0.0 {
+            for x in columns.clone() {
+                zbuffer[x] = distance_to_wall;
+            }
             let stake_height = (block_size as f32 * distance_to_projection_plane) / distance_to_wall;
             let stake_top = (hh - (stake_height / 2.0)).max(0.0) as usize;
             let stake_bottom = (hh + (stake_height / 2.0)).min(framebuffer.height as f32) as usize;
 
             // Estimate texture X coordinate based on ray direction and intersection distance
-            let texture_x = ((distance_to_wall / block_size as f32) * wall.width as f32) as u32 % wall.width;
+            let impact = intersect.intersect.impact;
+            let texture_u = distance_to_wall / block_size as f32;
+            let texture = cell_texture(impact);
+            // Versión de la textura según cuántos píxeles suyos caen en cada
+            // píxel de la pared en pantalla
+            let level = match (texture, settings.texture_filter) {
+                (Some(texture), Filter::Mipmap | Filter::Bilinear) => texture.mip_level(texture.height as f32 / stake_height),
+                _ => 0,
+            };
+            let column = texture
+                .filter(|_| settings.texture_filter != Filter::Bilinear)
+                .map(|texture| texture.column(level, texture_u, AddressMode::Wrap));
 
             for y in stake_top..stake_bottom {
-                // Calculate texture Y coordinate based on the current height in the column
-                let texture_y = ((y - stake_top) as f32 / (stake_bottom - stake_top) as f32 * wall.height as f32) as u32;
-
-                // Determine wall texture color based on cell type
-                let wall_color = cell_to_texture_color(intersect.intersect.impact, texture_x, texture_y);
+                // Determine wall texture color based on the current height in the column
+                let wall_color = match (texture, column) {
+                    (_, Some(column)) => column[(y - stake_top) * column.len() / (stake_bottom - stake_top)],
+                    (Some(texture), None) => {
+                        let texture_v = ((y - stake_top) as f32 + 0.5) / (stake_bottom - stake_top) as f32;
+                        texture.sample_bilinear(level, texture_u, texture_v, AddressMode::Wrap)
+                    }
+                    (None, None) => cell_color(impact),
+                };
 
-                framebuffer.set_current_color(wall_color);
-                if i < framebuffer.width && y < framebuffer.height {
-                    framebuffer.point(i, y);
+                framebuffer.set_current_color(apply_fog(wall_color, distance_to_wall, fog));
+                for x in columns.clone() {
+                    framebuffer.point(x as isize, y as isize);
                 }
             }
 
             // Draw ceiling and floor
-            if stake_top > 0 {
-                framebuffer.set_current_color(0x000000); // Black for the ceiling
-                for y in 0..stake_top {
-                    if i < framebuffer.width && y < framebuffer.height {
-                        framebuffer.point(i, y);
-                    }
-                }
-            }
+            let width = columns.len();
+            framebuffer.fill_rect(i as i32, 0, width, stake_top, 0x000000); // Black for the ceiling
+            framebuffer.fill_rect(i as i32, stake_bottom as i32, width, framebuffer.height - stake_bottom, 0xAAAAAA); // Gray for the floor
+        }
+    }
 
-            if stake_bottom < framebuffer.height as usize {
-                framebuffer.set_current_color(0xAAAAAA); // Gray for the floor
-                for y in stake_bottom..framebuffer.height as usize {
-                    if i < framebuffer.width && y < framebuffer.height {
-                        framebuffer.point(i, y);
-                    }
-                }
+    framebuffer.set_layer(LayerId::Sprites);
+    render_sprites(framebuffer, player, enemies, &zbuffer, block_size, fog, effects);
+}
+
+// Dibuja a los enemigos como imágenes planas que siempre miran al jugador. Se
+// pintan del más lejano al más cercano y cada columna solo si no hay una pared
+// más cerca; los bordes transparentes de la imagen se mezclan con el fondo.
+fn render_sprites(framebuffer: &mut Framebuffer, player: &Player, sprites: &[Vec2], zbuffer: &[f32], block_size: usize, fog: f32, effects: &ScreenEffects) {
+    let hh = framebuffer.height as f32 / 2.0 + effects.shake.1;
+    let view_angle = player.a + effects.shake.0 * player.fov / framebuffer.width as f32;
+    let distance_to_projection_plane = (framebuffer.width as f32 / 2.0) / (player.fov / 2.0).tan();
+
+    let mut sprites: Vec<(f32, f32)> = sprites
+        .iter()
+        .map(|pos| {
+            let delta = pos - player.pos;
+            // Ángulo respecto a la mirada del jugador, entre -PI y PI
+            let angle = (delta.y.atan2(delta.x) - view_angle + PI).rem_euclid(2.0 * PI) - PI;
+            (delta.norm(), angle)
+        })
+        .filter(|&(distance, angle)| distance > 1.0 && angle.abs() < player.fov)
+        .collect();
+    sprites.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    for (distance, angle) in sprites {
+        let height = block_size as f32 * distance_to_projection_plane / distance;
+        let width = height * jumpscare.width as f32 / jumpscare.height as f32;
+        // Los rayos se reparten por ángulo, así que la columna también
+        let center_x = (angle + player.fov / 2.0) / player.fov * framebuffer.width as f32;
+        let left = center_x - width / 2.0;
+        let top = hh - height / 2.0;
+
+        let first_x = left.max(0.0) as usize;
+        let last_x = ((left + width) as usize).min(framebuffer.width);
+        let first_y = top.max(0.0) as usize;
+        let last_y = ((top + height) as usize).min(framebuffer.height);
+        for (x, &wall_distance) in zbuffer.iter().enumerate().take(last_x).skip(first_x) {
+            if wall_distance < distance {
+                continue;
+            }
+            let tx = ((x as f32 - left) / width * jumpscare.width as f32) as i32;
+            for y in first_y..last_y {
+                let ty = ((y as f32 - top) / height * jumpscare.height as f32) as i32;
+                let color = jumpscare.sample(tx, ty, AddressMode::Clamp);
+                framebuffer.blend_point(x as i32, y as i32, apply_fog(color, distance, fog), (color >> 24) as u8);
             }
         }
     }
@@ -192,22 +330,24 @@ fn render_fps(framebuffer: &mut Framebuffer, fps: u32) {
     let scale = Scale::uniform(20.0); // Font size scale
     let color = 0xFFFFFF; // White color for the text
 
-    framebuffer.drawtext(&fps_text, x, y, scale, color);
+    framebuffer.drawtext(&fps_text, x, y, FontId::Nasa, scale, color);
 }
 
+// Si se pasa un framebuffer, el rayo se dibuja sobre él
 pub fn cast_ray(
-    framebuffer: &mut Framebuffer,
+    mut framebuffer: Option<&mut Framebuffer>,
     maze: &Vec<Vec<char>>,
     player: &Player,
     a: f32,
     block_size: usize,
-    draw_line: bool,
 ) -> CastRayResult {
     let mut d = 0.0;
     let mut hit_wall = false;
     let mut reached_goal = false; // Flag para la meta
 
-    framebuffer.set_current_color(0x0000FF); // Color azul para el rayo
+    if let Some(framebuffer) = framebuffer.as_mut() {
+        framebuffer.set_current_color(0x0000FF); // Color azul para el rayo
+    }
 
     loop {
         let cos = d * a.cos();
@@ -243,8 +383,8 @@ pub fn cast_ray(
                 };
             }
             ' ' => {
-                if draw_line {
-                    framebuffer.point(x, y);
+                if let Some(framebuffer) = framebuffer.as_mut() {
+                    framebuffer.point(x as isize, y as isize);
                 }
             }
             _ => {
@@ -259,20 +399,46 @@ pub fn cast_ray(
     }
 }
 
-fn play_background_music(stream_handle: &rodio::OutputStreamHandle) {
-    loop {
-        let music_file = File::open("music.mp3").unwrap();
-        let music_source = Decoder::new(BufReader::new(music_file)).unwrap();
-        
-        let music_sink = Sink::try_new(&stream_handle).unwrap();
-        music_sink.append(music_source);
-        
-        // Esperar hasta que la música termine de reproducirse
-        music_sink.sleep_until_end();
+// Cada superficie puede tener sus propias muestras, pasos_<superficie>_izq.mp3 y
+// pasos_<superficie>_der.mp3. Si no existen se usan dos pasos de pasos.mp3.
+fn load_footstep_sounds(audio: &mut dyn AudioBackend) {
+    for surface in Surface::ALL {
+        for side in ["izq", "der"] {
+            let sound_id = format!("pasos_{}_{}", surface.name(), side);
+            let file_path = format!("{}.mp3", sound_id);
+            if Path::new(&file_path).exists() {
+                audio.load(&sound_id, &file_path, Channel::Sfx);
+            }
+        }
     }
+
+    let step_length = Duration::from_millis(550);
+    audio.load_slice("pasos_izq", "pasos.mp3", Channel::Sfx, Duration::from_millis(750), step_length);
+    audio.load_slice("pasos_der", "pasos.mp3", Channel::Sfx, Duration::from_millis(1400), step_length);
 }
 
-fn render_minimap(framebuffer: &mut Framebuffer, maze: &[Vec<char>], block_size: usize, player: &Player) {
+// Carga los sonidos del nivel, arranca los emisores en bucle y cambia a la
+// música del nivel
+fn start_level_sounds(audio: &mut dyn AudioBackend, music: &mut MusicController, level: &LevelConfig, block_size: usize) {
+    if !audio.has_sound(&level.music) {
+        audio.load(&level.music, &level.music, Channel::Music);
+    }
+    music.play(audio, &level.music);
+
+    for (spot, channel) in level.emitters.iter().map(|spot| (spot, Channel::Ambience))
+        .chain(level.triggers.iter().map(|spot| (spot, Channel::Sfx)))
+    {
+        if !audio.has_sound(&spot.file) {
+            audio.load(&spot.file, &spot.file, channel);
+        }
+    }
+
+    for spot in &level.emitters {
+        audio.play_at(&spot.file, cell_center(spot.cell, block_size), true);
+    }
+}
+
+fn render_minimap(framebuffer: &mut Framebuffer, maze: &[Vec<char>], block_size: usize, player: &Player, collectibles: &[(usize, usize)], enemies: &[Vec2]) {
     let maze_width = maze[0].len();
     let maze_height = maze.len();
 
@@ -289,12 +455,7 @@ fn render_minimap(framebuffer: &mut Framebuffer, maze: &[Vec<char>], block_size:
     let minimap_y = 10; // Posición Y del minimapa
 
     // Dibuja el fondo del minimapa
-    framebuffer.set_current_color(0x222222); // Fondo oscuro para el minimapa
-    for x in minimap_x..(minimap_x + minimap_width) {
-        for y in minimap_y..(minimap_y + minimap_height) {
-            framebuffer.point(x, y);
-        }
-    }
+    framebuffer.fill_rect(minimap_x as i32, minimap_y as i32, minimap_width, minimap_height, 0x222222);
 
     // Dibuja el laberinto en el minimapa
     for row in 0..maze_height {
@@ -308,167 +469,186 @@ fn render_minimap(framebuffer: &mut Framebuffer, maze: &[Vec<char>], block_size:
         }
     }
 
-    // Dibuja la posición del jugador en el minimapa
-    framebuffer.set_current_color(0xFF0000); // Color rojo para el jugador
-    let player_x = (player.pos.x * scale) as usize;
-    let player_y = (player.pos.y * scale) as usize;
-    framebuffer.point(minimap_x + player_x, minimap_y + player_y);
-}
+    // Dibuja los objetos que faltan recoger
+    for &cell in collectibles {
+        let center = cell_center(cell, block_size);
+        draw_collectible(framebuffer, (minimap_x as f32 + center.x * scale) as i32, (minimap_y as f32 + center.y * scale) as i32, 1);
+    }
 
-fn scale_texture(texture: &Texture, scale: f32) -> Vec<u32> {
-    let original_width = texture.width as usize;
-    let original_height = texture.height as usize;
-    let new_width = (original_width as f32 * scale) as usize;
-    let new_height = (original_height as f32 * scale) as usize;
+    for enemy in enemies {
+        draw_enemy_marker(framebuffer, (minimap_x as f32 + enemy.x * scale) as i32, (minimap_y as f32 + enemy.y * scale) as i32, 1);
+    }
 
-    let mut scaled_texture = vec![0; new_width * new_height];
+    // Dibuja al jugador en el minimapa, con una línea hacia donde mira
+    let player_x = minimap_x as f32 + player.pos.x * scale;
+    let player_y = minimap_y as f32 + player.pos.y * scale;
+    framebuffer.line_aa(player_x, player_y, player_x + 8.0 * player.a.cos(), player_y + 8.0 * player.a.sin(), 0xFF0000);
+    framebuffer.fill_circle(player_x as i32, player_y as i32, 1, 0xFF0000); // Color rojo para el jugador
+}
 
-    for new_y in 0..new_height {
-        for new_x in 0..new_width {
-            // Calcular las coordenadas de la textura original
-            let orig_x = (new_x as f32 / scale) as usize;
-            let orig_y = (new_y as f32 / scale) as usize;
+fn render_jumpscare(framebuffer: &mut Framebuffer, player: &Player, block_size: usize, variant: u32, opacity: f32) {
+    // Cada variante del susto tiene su propia imagen
+    let texture = &SCARE_TEXTURES[variant as usize % SCARE_TEXTURES.len()];
 
-            // Obtener el color del píxel de la textura original
-            let color = texture.get_pixel_color(orig_x as u32, orig_y as u32);
+    // Escalar la textura del enemigo (por ejemplo, reducir al 50% del tamaño original)
+    let scale = 0.5;
+    let width = (texture.width as f32 * scale) as usize;
+    let height = (texture.height as f32 * scale) as usize;
+
+    // Centrada en la pantalla; aparece con un fundido según la opacidad
+    let x = framebuffer.width as i32 / 2 - width as i32 / 2;
+    let y = framebuffer.height as i32 / 2 - height as i32 / 2;
+    framebuffer.set_opacity(opacity);
+    framebuffer.blit(texture, None, Rect::new(x, y, width, height), None);
+    framebuffer.set_opacity(1.0);
+}
 
-            // Establecer el color en la textura escalada
-            scaled_texture[new_y * new_width + new_x] = color;
-        }
-    }
 
-    scaled_texture
+// Resolución interna con la que se dibuja, independiente del laberinto y de
+// la ventana. La ventana la estira sin deformarla, con franjas negras si hace falta.
+const DEFAULT_RESOLUTION: (usize, usize) = (960, 540);
+
+// Opciones de línea de comandos
+struct Options {
+    record: Option<String>,
+    replay: Option<String>,
+    headless: bool,
+    no_audio: bool,
+    seed: Option<u64>,
+    resolution: (usize, usize),
 }
 
-fn render_jumpscare(framebuffer: &mut Framebuffer, player: &Player, block_size: usize) {
-    // Tamaño de la textura del enemigo
-    let texture_width = jumpscare.width as f32;
-    let texture_height = jumpscare.height as f32;
+// Lee un tamaño como "1280x720"
+fn parse_resolution(text: &str) -> Option<(usize, usize)> {
+    let (width, height) = text.split_once('x')?;
+    let resolution = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+    (resolution.0 > 0 && resolution.1 > 0).then_some(resolution)
+}
 
-    // Escalar la textura del enemigo (por ejemplo, reducir al 50% del tamaño original)
-    let scale = 0.5;
-    let scaled_texture = scale_texture(&jumpscare, scale);
-
-    // Tamaño de la textura escalada
-    let scaled_width = (texture_width * scale) as i32;
-    let scaled_height = (texture_height * scale) as i32;
-
-    // Posición central de la pantalla
-    let center_x = framebuffer.width as i32 / 2;
-    let center_y = framebuffer.height as i32 / 2;
-
-    // Calcular la posición en el framebuffer para centrar la textura escalada
-    let render_x = center_x - scaled_width / 2;
-    let render_y = center_y - scaled_height / 2;
-
-    for y in 0..scaled_height {
-        for x in 0..scaled_width {
-            let color = scaled_texture[(y * scaled_width + x) as usize];
-            if color != 0xFFFFFF { // Ignorar el color negro
-                let screen_x = render_x + x;
-                let screen_y = render_y + y;
-
-                // Verificar que las coordenadas están dentro del framebuffer
-                if screen_x >= 0 && screen_x < framebuffer.width as i32 &&
-                   screen_y >= 0 && screen_y < framebuffer.height as i32 {
-                    framebuffer.set_current_color(color);
-                    framebuffer.point(screen_x as usize, screen_y as usize);
-                }
-            }
+fn parse_options() -> Options {
+    let mut options = Options { record: None, replay: None, headless: false, no_audio: false, seed: None, resolution: DEFAULT_RESOLUTION };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => options.record = args.next(),
+            "--replay" => options.replay = args.next(),
+            "--headless" => options.headless = true,
+            "--no-audio" => options.no_audio = true,
+            "--seed" => options.seed = args.next().and_then(|seed| seed.parse().ok()),
+            "--resolution" => match args.next().as_deref().and_then(parse_resolution) {
+                Some(resolution) => options.resolution = resolution,
+                None => eprintln!("--resolution espera un tamaño como 1280x720"),
+            },
+            _ => eprintln!("Opción desconocida: {}", arg),
         }
     }
+    options
 }
 
+fn print_replay_summary(game: &Game) {
+    println!(
+        "Repetición de {}: {} ticks, posición ({:.1}, {:.1}), ángulo {:.3}, meta alcanzada: {}, muerto: {}",
+        game.maze_file, game.tick, game.player.pos.x, game.player.pos.y, game.player.a, game.reached_goal, game.dead
+    );
+}
 
-fn main() {
-    let mut maze_file = "maze1.txt"; // Laberinto por defecto
-
-    // Cargar el laberinto y obtener sus dimensiones
-    let mut maze = load_maze(maze_file);
-    let mut height = maze.len();
-    let mut width = maze[0].len();
-    let block_size = 50; // Tamaño de cada bloque en píxeles
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let stream_handle = Arc::new(stream_handle); 
+// Música del menú; si no existe menu.mp3 se usa la de los niveles
+fn load_menu_music(audio: &mut dyn AudioBackend) -> &'static str {
+    if Path::new("menu.mp3").exists() {
+        audio.load("menu.mp3", "menu.mp3", Channel::Music);
+        "menu.mp3"
+    } else {
+        audio.load("music.mp3", "music.mp3", Channel::Music);
+        "music.mp3"
+    }
+}
 
-    // Crear el framebuffer con las dimensiones adecuadas
-    let mut framebuffer = Framebuffer::new(width * block_size, height * block_size);
+fn main() {
+    let options = parse_options();
+
+    // Repetición sin ventana ni audio, útil como prueba de regresión
+    if options.headless {
+        let Some(replay_path) = &options.replay else {
+            eprintln!("--headless requiere --replay <archivo>");
+            return;
+        };
+        let replay = Replay::load(replay_path).unwrap_or_else(|e| {
+            panic!("No se pudo leer la repetición {}: {}", replay_path, e);
+        });
+        print_replay_summary(&Game::run_replay(&replay));
+        return;
+    }
 
-    let mut welcome_window = Window::new(
-        "Bienvenido a Laberinto",
-        framebuffer.width,
-        framebuffer.height,
-        WindowOptions::default(),
-    )
-    .unwrap_or_else(|e| {
-        panic!("Window creation failed: {}", e);
+    let replay = options.replay.as_ref().map(|replay_path| {
+        Replay::load(replay_path).unwrap_or_else(|e| {
+            panic!("No se pudo leer la repetición {}: {}", replay_path, e);
+        })
     });
 
-    let mut welcome_buffer = vec![0; framebuffer.width * framebuffer.height];
-
-    while welcome_window.is_open() && !welcome_window.is_key_down(Key::Enter) {
-        let start_time = Instant::now();
-        
-        // Dibujar un color de fondo
-        for i in 0..welcome_buffer.len() {
-            welcome_buffer[i] = 0x000000; // Negro
-        }
-    
-        // Dibujar texto en el buffer de bienvenida
-        let scale = Scale::uniform(32.0);
-        let text = "Bienvenido, Elige el nivel para jugar \n Presiona 1 para nivel 1 \n Presiona 2 para nivel 2 \n Presiona 3 para nivel 3";
-        framebuffer.clear();
-        framebuffer.drawtext(&text, 10, 10, scale, 0xFFFFFF); // Asegurarse que el color es 0xFFFFFF para blanco
+    let (width, height) = options.resolution;
+    let block_size = 50; // Tamaño de cada bloque en píxeles
 
-        
-    
-        // Actualizar el contenido de `welcome_buffer`
-        welcome_window.update_with_buffer(&framebuffer.buffer, framebuffer.width, framebuffer.height).unwrap();
-        
-        // Manejar la entrada del teclado
-        if welcome_window.is_key_down(Key::Key1) {
-            maze_file = "maze1.txt";
-            break; 
-        } else if welcome_window.is_key_down(Key::Key2) {
-            maze_file = "maze2.txt";
-            break; 
-        } else if welcome_window.is_key_down(Key::Key3) {
-            maze_file = "maze3.txt";
-            break;
+    // Todos los sonidos se decodifican una vez al inicio
+    let mut audio = audio::open(options.no_audio);
+    let menu_music = load_menu_music(audio.as_mut());
+    load_footstep_sounds(audio.as_mut());
+    for (index, (sound_id, file)) in SCARE_SOUNDS.iter().enumerate() {
+        if index == 0 || Path::new(file).exists() {
+            audio.load(sound_id, file, Channel::Sfx);
         }
     }
-    
-    // Cerrar la ventana de bienvenida y proceder a la ventana principal
-    drop(welcome_window);
-
-    // Cargar el laberinto y obtener sus dimensiones
-    maze = load_maze(maze_file);
-    height = maze.len();
-    width = maze[0].len();
-
-    // Variables para controlar el tiempo de aparición del enemigo
-    let mut last_jumpscare_spawn = Instant::now();
-    let jumpscare_spawn_interval = Duration::new(13, 0);
-    let jumpscare_display_time = Duration::new(2, 0);
-    let mut show_jumpscare = false;
-
-    // Inicializar el jugador en la posición del carácter 'p'
-    let mut player_pos = (0.0, 0.0);
-    for (y, row) in maze.iter().enumerate() {
-        for (x, &cell) in row.iter().enumerate() {
-            if cell == 'p' {
-                player_pos = (x as f32 * block_size as f32, y as f32 * block_size as f32);
-            }
-        }
+    if Path::new("recoger.mp3").exists() {
+        audio.load("recoger", "recoger.mp3", Channel::Sfx);
     }
-    let mut player = Player::new(player_pos.0, player_pos.1, PI / 3.0, PI / 3.0);
 
-    let mut window_game = Window::new(
-        "Laberinto - Framebuffer",
+    let mut app = App {
+        audio,
+        music: MusicController::new(),
+        menu_music,
+        settings: Settings::default(),
+        campaign: Campaign::load("campaign.txt"),
+        progress: Progress::load(PROGRESS_FILE),
+        leaderboard: Leaderboard::load(RECORDS_FILE),
+        block_size,
+        record_path: options.record,
+        seed: options.seed,
+        fps: 0,
+        screen_size: (width, height),
+        window_size: (width, height),
+    };
+    app.settings.apply(app.audio.as_mut());
+
+    // Avisar de una vez todo lo que falta, en lugar de descubrirlo en medio de un nivel
+    let missing = missing_assets(&app.campaign);
+    if !missing.is_empty() {
+        eprintln!("Faltan {} archivos; el juego usa reemplazos o sigue sin esos sonidos:", missing.len());
+        for file in &missing {
+            eprintln!("  {}", file);
+        }
+    }
+    // Las texturas se cargan ahora y no en el primer cuadro del nivel
+    Lazy::force(&SCARE_TEXTURES);
+    Lazy::force(&wall);
+    Lazy::force(&corner);
+
+    // Crear el framebuffer con la resolución interna
+    let mut framebuffer = Framebuffer::new(width, height);
+
+    // Una repetición ya trae su nivel, así que no se muestra la bienvenida
+    let first: Box<dyn Scene> = match replay {
+        Some(replay) => Box::new(Playing::from_replay(&mut app, replay)),
+        None => Box::new(Menu::new(&mut app)),
+    };
+    let mut scenes = SceneStack::new(first);
+
+    // Una sola ventana para el menú, la partida y las pantallas finales
+    let mut window = Window::new(
+        "Laberinto",
         framebuffer.width,
         framebuffer.height,
         WindowOptions {
             resize: true,
+            scale_mode: ScaleMode::AspectRatioStretch,
             ..WindowOptions::default()
         },
     )
@@ -476,126 +656,62 @@ fn main() {
         panic!("Window creation failed: {}", e);
     });
 
-    let mut mode = "3D";
-    let mut game_over = false;
+    let mut input = InputState::new();
 
     // Frame timing
-    let mut last_fps_update = Instant::now();
-    let fps_update_interval = Duration::new(1, 0);
     let frame_duration = Duration::new(1, 0) / 60;
+    let mut last_frame = Instant::now();
 
-    // Crear una variable compartida para controlar el sonido del jumpscare
-    let sound_played = Arc::new(Mutex::new(false));
-
-    let stream_handle_clone = Arc::clone(&stream_handle);
-    thread::spawn(move || {
-        play_background_music(&stream_handle_clone);
-    });
-
-    while window_game.is_open() && !window_game.is_key_down(Key::Escape) && !game_over {
+    while window.is_open() && !scenes.is_empty() {
         let start_time = Instant::now();
-
-        // Toggle mode
-        if window_game.is_key_down(Key::M) {
-            mode = if mode == "2D" { "3D" } else { "2D" };
+        let dt = start_time.duration_since(last_frame);
+        last_frame = start_time;
+        input.update(&window);
+        app.window_size = window.get_size();
+
+        // Silenciar el audio
+        if input.is_pressed(Key::N) {
+            app.settings.muted = !app.settings.muted;
+            app.settings.apply(app.audio.as_mut());
         }
 
-        // Process events
-        process_events(&window_game, &mut player, maze_file);
+        scenes.update(&mut app, &input, dt);
+        app.music.update(app.audio.as_mut(), dt.as_secs_f32());
+        scenes.render(&app, &mut framebuffer);
 
-        if !game_over {
-            framebuffer.clear();
-            if mode == "2D" {
-                render2D(&mut framebuffer, &player, maze_file);
-            } else {
-                let cast_result = cast_ray(&mut framebuffer, &maze, &player, player.a, block_size, false);
-                if cast_result.reached_goal {
-                    game_over = true;
-                }
-                render3D(&mut framebuffer, &player, maze_file);
-            }
-
-            render_minimap(&mut framebuffer, &maze, block_size, &player);
-
-            // Control del tiempo de aparición del enemigo
-            let now = Instant::now();
-            if now.duration_since(last_jumpscare_spawn) >= jumpscare_spawn_interval {
-                last_jumpscare_spawn = now;
-                show_jumpscare = true;
-                // Reproducir el sonido del jumpscare en un hilo separado
-                let sound_played_clone = Arc::clone(&sound_played);
-                let stream_handle_clone = Arc::clone(&stream_handle);
-                thread::spawn(move || {
-                    let enemy_sound = Decoder::new(File::open("screamer.mp3").unwrap()).unwrap();
-                    let mut enemy_sink = Sink::try_new(&*stream_handle_clone).unwrap();
-                    enemy_sink.append(enemy_sound);
-                    enemy_sink.sleep_until_end();
-                    *sound_played_clone.lock().unwrap() = false;
-                });
-            }
+        // Ver las capas de a una y ocultar la de depuración
+        if input.is_pressed(Key::F3) {
+            framebuffer.cycle_solo_layer();
+        }
+        // El nombre de la capa que se ve sola va sobre ella misma
+        if let Some(layer) = framebuffer.solo_layer() {
+            framebuffer.set_layer(layer);
+            let y = framebuffer.height.saturating_sub(30);
+            framebuffer.drawtext(&format!("Capa: {:?}", layer), 10, y, FontId::Nasa, Scale::uniform(20.0), 0xFFFF00);
+        }
+        if input.is_pressed(Key::F4) {
+            let visible = framebuffer.is_layer_visible(LayerId::Debug);
+            framebuffer.set_layer_visible(LayerId::Debug, !visible);
+        }
+        framebuffer.compose();
 
-            // Renderizar el enemigo si es el momento adecuado
-            if show_jumpscare && now.duration_since(last_jumpscare_spawn) <= jumpscare_display_time {
-                render_jumpscare(&mut framebuffer, &player, block_size);
-            } else if now.duration_since(last_jumpscare_spawn) > jumpscare_display_time {
-                show_jumpscare = false;
-            }
+        // Captura de pantalla
+        if input.is_pressed(Key::F12) {
+            framebuffer.render_buffer("captura.bmp");
+        }
 
-            // Calculate FPS and render it
-            let elapsed = start_time.elapsed();
-            let fps = (1.0 / elapsed.as_secs_f32()).round() as u32;
-            render_fps(&mut framebuffer, fps);
+        // Update window with framebuffer
+        window.update_with_buffer(framebuffer.frame(), framebuffer.width, framebuffer.height).unwrap();
 
-            // Update window with framebuffer
-            window_game.update_with_buffer(&framebuffer.buffer, framebuffer.width, framebuffer.height).unwrap();
-        } else {
-            framebuffer.clear();
-            window_game.update_with_buffer(&framebuffer.buffer, framebuffer.width, framebuffer.height).unwrap();
-        }
+        // Calculate FPS
+        let frame_time = Instant::now().duration_since(start_time);
+        app.fps = (1.0 / frame_time.as_secs_f32()).round() as u32;
 
         // Esperar hasta el siguiente cuadro para mantener el FPS
-        let frame_time = Instant::now().duration_since(start_time);
         if frame_time < frame_duration {
             std::thread::sleep(frame_duration - frame_time);
         }
     }
-    drop(window_game);
 
-    let mut screen = Window::new(
-        "FELICITACIONES",
-        framebuffer.width,
-        framebuffer.height,
-        WindowOptions::default(),
-    )
-    .unwrap_or_else(|e| {
-        panic!("Window creation failed: {}", e);
-    });
-
-    if game_over {
-        while screen.is_open() && !screen.is_key_down(Key::Enter) && !screen.is_key_down(Key::Escape) {
-            let start_time = Instant::now();
-            
-            // Dibujar un color de fondo
-            for i in 0..framebuffer.buffer.len() {
-                framebuffer.buffer[i] = 0x000000; // Negro
-            }
-    
-            // Dibujar texto en el buffer de bienvenida
-            let scale = Scale::uniform(32.0);
-            let text = "FELICIDADES, GANASTE";
-            framebuffer.drawtext(&text, 10, 10, scale, 0xFFFFFF); // Asegurarse que el color es 0xFFFFFF para blanco
-    
-            // Actualizar el contenido de `framebuffer`
-            screen.update_with_buffer(&framebuffer.buffer, framebuffer.width, framebuffer.height).unwrap();
-    
-            // Opcional: Manejar la entrada del teclado para cerrar la ventana
-            if screen.is_key_down(Key::Enter) || screen.is_key_down(Key::Escape) {
-                break; // Salir del bucle si se presiona Enter o Escape
-            }
-        }
-    }
-    
-    
-    // Cerrar la ventana de bienvenida y proceder a la ventana principal
-    drop(screen);
-}
\ No newline at end of file
+    app.music.stop(app.audio.as_mut());
+}
@@ -0,0 +1,444 @@
+use std::io;
+use std::time::Duration;
+use minifb::Key;
+use rusttype::Scale;
+use crate::fonts::FontId;
+use crate::text::{measure_text, Align, Span, TextStyle};
+use crate::framebuffer::Framebuffer;
+use crate::input::InputState;
+use crate::game::{Game, TICK_RATE};
+use crate::playing::Playing;
+use crate::savegame::{slot_path, SaveGame, SLOTS};
+use crate::score::{Record, Score, RECORDS_FILE};
+use crate::scene::{App, Scene, Transition};
+use crate::settings::Settings;
+
+// Teclas para elegir directamente uno de los primeros niveles
+const NUMBER_KEYS: [Key; 9] = [
+    Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+];
+
+// Pantalla de bienvenida donde se elige el nivel de la campaña
+pub struct Menu {
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new(app: &mut App) -> Menu {
+        app.music.set_ducked(false);
+        app.music.play(app.audio.as_mut(), app.menu_music);
+
+        // Empieza en el primer nivel desbloqueado que falta completar
+        let levels = &app.campaign.levels;
+        let selected = (0..levels.len())
+            .find(|&index| app.progress.is_unlocked(&app.campaign, index) && !app.progress.is_completed(&levels[index].file))
+            .unwrap_or(0);
+        Menu { selected }
+    }
+}
+
+impl Scene for Menu {
+    fn update(&mut self, app: &mut App, input: &InputState, _dt: Duration) -> Transition {
+        if input.is_pressed(Key::Escape) {
+            return Transition::Quit;
+        }
+        if input.is_pressed(Key::S) {
+            return Transition::Push(Box::new(SettingsMenu::new()));
+        }
+        if input.is_pressed(Key::C) {
+            return Transition::Push(Box::new(LoadMenu::new()));
+        }
+
+        // Solo se puede mover la selección entre niveles desbloqueados
+        let unlocked = (0..app.campaign.levels.len())
+            .take_while(|&index| app.progress.is_unlocked(&app.campaign, index))
+            .count();
+        if input.is_pressed(Key::Down) && self.selected + 1 < unlocked {
+            self.selected += 1;
+        }
+        if input.is_pressed(Key::Up) && self.selected > 0 {
+            self.selected -= 1;
+        }
+
+        let mut chosen = None;
+        if input.is_released(Key::Enter) {
+            chosen = Some(self.selected);
+        }
+        for (index, key) in NUMBER_KEYS.iter().enumerate() {
+            if input.is_pressed(*key) && index < unlocked {
+                chosen = Some(index);
+            }
+        }
+
+        match chosen.and_then(|index| app.campaign.levels.get(index)) {
+            Some(level) => {
+                let maze_file = level.file.clone();
+                Transition::Reset(Box::new(Playing::new(app, &maze_file)))
+            }
+            None => Transition::None,
+        }
+    }
+
+    fn render(&self, app: &App, framebuffer: &mut Framebuffer) {
+        framebuffer.clear();
+        let title = TextStyle::new(FontId::Sixty, 36.0, 0xFFFFFF).align(Align::Center, Align::Start).shadow(0x444444);
+        framebuffer.draw_text("Bienvenido, elige el nivel para jugar", framebuffer.width as i32 / 2, 10, &title);
+
+        for (index, level) in app.campaign.levels.iter().enumerate() {
+            let (status, color) = if !app.progress.is_unlocked(&app.campaign, index) {
+                (" (bloqueado)", 0x666666)
+            } else if index == self.selected {
+                ("", 0xFFFF00)
+            } else {
+                ("", 0xFFFFFF)
+            };
+            let mark = if app.progress.is_completed(&level.file) { " *" } else { "" };
+            let text = format!("{}. {}{}{}", index + 1, level.name, mark, status);
+            framebuffer.drawtext(&text, 30, 70 + index * 36, FontId::Nasa, Scale::uniform(28.0), color);
+        }
+
+        let help_y = 70 + app.campaign.levels.len() * 36 + 20;
+        let help = TextStyle::new(FontId::Nasa, 20.0, 0xAAAAAA).wrap(framebuffer.width.saturating_sub(20).max(1));
+        framebuffer.draw_text("Flechas y Enter: jugar   C: cargar partida   S: opciones   Esc: salir", 10, help_y as i32, &help);
+    }
+}
+
+// Lista vertical de opciones que se recorre con las flechas y Enter, o pasando
+// el mouse por encima y haciendo clic
+pub struct ItemList {
+    pub selected: usize,
+    count: usize,
+    x: usize,
+    top: usize,
+    spacing: usize,
+    last_mouse: Option<(usize, usize)>,
+}
+
+impl ItemList {
+    pub fn new(count: usize, x: usize, top: usize, spacing: usize) -> ItemList {
+        ItemList { selected: 0, count, x, top, spacing, last_mouse: None }
+    }
+
+    fn item_at(&self, (x, y): (usize, usize)) -> Option<usize> {
+        if x < self.x || y < self.top {
+            return None;
+        }
+        let item = (y - self.top) / self.spacing;
+        (item < self.count).then_some(item)
+    }
+
+    // Devuelve la opción elegida en este cuadro, con Enter o con un clic
+    pub fn update(&mut self, app: &App, input: &InputState) -> Option<usize> {
+        if input.is_pressed(Key::Down) {
+            self.selected = (self.selected + 1) % self.count;
+        }
+        if input.is_pressed(Key::Up) {
+            self.selected = (self.selected + self.count - 1) % self.count;
+        }
+
+        // El mouse solo cambia la selección cuando se mueve, para no pelear con el teclado
+        let mouse = app.mouse_position(input);
+        let hovered = mouse.and_then(|pos| self.item_at(pos));
+        if mouse != self.last_mouse {
+            if let Some(item) = hovered {
+                self.selected = item;
+            }
+            self.last_mouse = mouse;
+        }
+
+        if input.is_released(Key::Enter) {
+            return Some(self.selected);
+        }
+        if input.is_clicked() {
+            return hovered;
+        }
+        None
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer, items: &[String], scale: f32) {
+        for (index, item) in items.iter().enumerate() {
+            let color = if index == self.selected { 0xFFFF00 } else { 0xFFFFFF };
+            framebuffer.drawtext(item, self.x, self.top + index * self.spacing, FontId::Nasa, Scale::uniform(scale), color);
+        }
+    }
+}
+
+// Lista de ranuras de guardado. Una partida dañada o de otra versión se
+// muestra con su error en lugar de cargarse.
+pub struct LoadMenu {
+    slots: Vec<io::Result<SaveGame>>,
+    message: String,
+}
+
+impl LoadMenu {
+    pub fn new() -> LoadMenu {
+        let slots = (0..SLOTS).map(|slot| SaveGame::load(&slot_path(slot))).collect();
+        LoadMenu { slots, message: String::new() }
+    }
+}
+
+impl Scene for LoadMenu {
+    fn update(&mut self, app: &mut App, input: &InputState, _dt: Duration) -> Transition {
+        if input.is_pressed(Key::Escape) {
+            return Transition::Pop;
+        }
+        for (slot, key) in NUMBER_KEYS.iter().take(SLOTS).enumerate() {
+            if !input.is_pressed(*key) {
+                continue;
+            }
+            let Ok(save) = &self.slots[slot] else {
+                self.message = format!("La ranura {} no se puede cargar", slot + 1);
+                continue;
+            };
+            match Game::from_save(save) {
+                Ok(game) => return Transition::Reset(Box::new(Playing::resume(app, game))),
+                Err(e) => self.message = format!("No se pudo cargar: {}", e),
+            }
+        }
+        Transition::None
+    }
+
+    fn render(&self, app: &App, framebuffer: &mut Framebuffer) {
+        framebuffer.clear();
+        framebuffer.drawtext("CARGAR PARTIDA", 10, 10, FontId::Sixty, Scale::uniform(32.0), 0xFFFFFF);
+        for (slot, save) in self.slots.iter().enumerate() {
+            let (text, color) = match save {
+                Ok(save) => {
+                    let seconds = save.tick / TICK_RATE as u64;
+                    let name = app.campaign.index_of(&save.level)
+                        .map_or(save.level.as_str(), |index| app.campaign.levels[index].name.as_str());
+                    (format!("{}. {} - {}:{:02}", slot + 1, name, seconds / 60, seconds % 60), 0xFFFFFF)
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => (format!("{}. vacia", slot + 1), 0x666666),
+                Err(e) => (format!("{}. no se puede cargar: {}", slot + 1, e), 0xFF6666),
+            };
+            framebuffer.drawtext(&text, 30, 70 + slot * 36, FontId::Nasa, Scale::uniform(24.0), color);
+        }
+        let help_y = 70 + SLOTS * 36 + 20;
+        framebuffer.drawtext("1, 2, 3: cargar   Esc: volver", 10, help_y, FontId::Nasa, Scale::uniform(20.0), 0xAAAAAA);
+        let message = TextStyle::new(FontId::Nasa, 20.0, 0xFFFF00).wrap(framebuffer.width.saturating_sub(20).max(1));
+        framebuffer.draw_text(&self.message, 10, help_y as i32 + 30, &message);
+    }
+}
+
+// Opciones del juego. Los cambios se aplican en cuanto se hacen; la última
+// opción de la lista vuelve a la pantalla anterior.
+pub struct SettingsMenu {
+    list: ItemList,
+}
+
+impl SettingsMenu {
+    pub fn new() -> SettingsMenu {
+        SettingsMenu { list: ItemList::new(Settings::ITEMS + 1, 30, 70, 40) }
+    }
+}
+
+impl Scene for SettingsMenu {
+    fn update(&mut self, app: &mut App, input: &InputState, _dt: Duration) -> Transition {
+        if input.is_pressed(Key::Escape) {
+            return Transition::Pop;
+        }
+
+        let item = self.list.selected;
+        match self.list.update(app, input) {
+            Some(chosen) if chosen == Settings::ITEMS => return Transition::Pop,
+            Some(chosen) => app.settings.adjust(chosen, true, true),
+            None if item < Settings::ITEMS && input.is_pressed(Key::Right) => app.settings.adjust(item, true, false),
+            None if item < Settings::ITEMS && input.is_pressed(Key::Left) => app.settings.adjust(item, false, false),
+            None => return Transition::None,
+        }
+        app.settings.apply(app.audio.as_mut());
+        Transition::None
+    }
+
+    fn render(&self, app: &App, framebuffer: &mut Framebuffer) {
+        let mut items: Vec<String> = (0..Settings::ITEMS).map(|item| app.settings.describe(item)).collect();
+        items.push("Volver".to_string());
+
+        framebuffer.clear();
+        framebuffer.drawtext("OPCIONES", 10, 10, FontId::Sixty, Scale::uniform(32.0), 0xFFFFFF);
+        self.list.render(framebuffer, &items, 24.0);
+        let help_y = 70 + items.len() * 40 + 20;
+        framebuffer.drawtext("Flechas o clic: cambiar   Esc: volver", 10, help_y, FontId::Nasa, Scale::uniform(20.0), 0xAAAAAA);
+    }
+}
+
+// Intermedio al llegar a la meta: muestra el puntaje y la tabla del nivel, pide
+// las iniciales si el puntaje entra en la tabla y ofrece seguir con el
+// siguiente nivel
+pub struct LevelComplete {
+    maze_file: String,
+    next: Option<String>,
+    score: Score,
+    ticks: u64,
+    steps: u32,
+    scares: u32,
+    collected: (usize, usize),
+    table: Option<String>, // Sin tabla el puntaje no se registra, como en las repeticiones
+    initials: Option<String>, // Iniciales que se están escribiendo
+    record: Option<Record>,   // Puntaje ya registrado, para resaltarlo
+}
+
+impl LevelComplete {
+    pub fn new(app: &mut App, game: &Game, table: Option<String>) -> LevelComplete {
+        app.music.set_ducked(false);
+        app.music.play(app.audio.as_mut(), app.menu_music);
+        let score = Score::of(game);
+        let initials = table
+            .as_ref()
+            .filter(|table| app.leaderboard.qualifies(table, score.total))
+            .map(|_| String::new());
+        LevelComplete {
+            maze_file: game.maze_file.clone(),
+            next: app.campaign.next_after(&game.maze_file).map(|level| level.file.clone()),
+            score,
+            ticks: game.tick,
+            steps: game.steps,
+            scares: game.scares,
+            collected: (game.collected.iter().filter(|&&collected| collected).count(), game.collected.len()),
+            table,
+            initials,
+            record: None,
+        }
+    }
+
+    fn level_name<'a>(app: &'a App, file: &'a str) -> &'a str {
+        app.campaign.index_of(file).map_or(file, |index| app.campaign.levels[index].name.as_str())
+    }
+
+    // Escribe las iniciales; devuelve true mientras el campo siga abierto
+    fn type_initials(&mut self, app: &mut App, input: &InputState) -> bool {
+        let Some(initials) = self.initials.as_mut() else {
+            return false;
+        };
+        if let Some(letter) = input.typed_letter() {
+            if initials.len() < 3 {
+                initials.push(letter);
+            }
+        }
+        if input.is_pressed(Key::Backspace) {
+            initials.pop();
+        }
+        if input.is_pressed(Key::Escape) {
+            self.initials = None;
+        } else if input.is_released(Key::Enter) && !initials.is_empty() {
+            let record = Record { initials: initials.clone(), score: self.score.total, ticks: self.ticks };
+            let table = self.table.as_deref().unwrap_or(&self.maze_file);
+            app.leaderboard.add(table, record.clone());
+            if let Err(e) = app.leaderboard.save(RECORDS_FILE) {
+                eprintln!("No se pudieron guardar los puntajes en {}: {}", RECORDS_FILE, e);
+            }
+            self.record = Some(record);
+            self.initials = None;
+        }
+        true
+    }
+}
+
+impl Scene for LevelComplete {
+    fn update(&mut self, app: &mut App, input: &InputState, _dt: Duration) -> Transition {
+        if self.type_initials(app, input) {
+            return Transition::None;
+        }
+        if input.is_pressed(Key::R) {
+            return Transition::Replace(Box::new(Playing::new(app, &self.maze_file)));
+        }
+        if input.is_released(Key::Enter) {
+            return match &self.next {
+                Some(next) => Transition::Replace(Box::new(Playing::new(app, next))),
+                None => Transition::Reset(Box::new(Menu::new(app))),
+            };
+        }
+        if input.is_pressed(Key::Escape) {
+            return Transition::Reset(Box::new(Menu::new(app)));
+        }
+        Transition::None
+    }
+
+    fn render(&self, app: &App, framebuffer: &mut Framebuffer) {
+        framebuffer.clear();
+        let title = format!("FELICIDADES, GANASTE\n{}", LevelComplete::level_name(app, &self.maze_file));
+        let title_style = TextStyle::new(FontId::Sixty, 32.0, 0xFFFFFF).align(Align::Center, Align::Start).shadow(0x444444);
+        framebuffer.draw_text(&title, framebuffer.width as i32 / 2, 10, &title_style);
+
+        let score = &self.score;
+        let summary = format!(
+            "Tiempo {}   Pasos {}   Sustos {}   Objetos {}/{}\nTiempo {:+}   Pasos {:+}   Sustos {:+}   Objetos {:+}\nPUNTOS: ",
+            format_time(self.ticks), self.steps, self.scares, self.collected.0, self.collected.1,
+            score.time, score.steps, score.scares, score.collectibles,
+        );
+        let total = score.total.to_string();
+        let spans = [Span { text: &summary, color: 0xFFFFFF }, Span { text: &total, color: 0xFFFF00 }];
+        // El bloque queda centrado pero las líneas alineadas a la izquierda
+        let style = TextStyle::new(FontId::Nasa, 22.0, 0xFFFFFF);
+        let bounds = measure_text(&(summary.clone() + &total), 0, 0, &style);
+        let x = framebuffer.width.saturating_sub(bounds.width) / 2;
+        framebuffer.draw_spans(&spans, x as i32, 100, &style);
+
+        // Tabla de puntajes del nivel
+        let table = self.table.as_deref().unwrap_or(&self.maze_file);
+        let mut y = 200;
+        framebuffer.drawtext("MEJORES PUNTAJES", 10, y, FontId::Nasa, Scale::uniform(24.0), 0xFFFFFF);
+        for (index, record) in app.leaderboard.top(table).iter().enumerate() {
+            y += 28;
+            let color = if self.record.as_ref() == Some(*record) { 0xFFFF00 } else { 0xFFFFFF };
+            let text = format!("{}. {:<3}  {:>6}  {}", index + 1, record.initials, record.score, format_time(record.ticks));
+            framebuffer.drawtext(&text, 30, y, FontId::Nasa, Scale::uniform(22.0), color);
+        }
+
+        y += 50;
+        let prompt = match &self.initials {
+            Some(initials) => format!("Nuevo record! Escribe tus iniciales: {}_ \n Enter: guardar   Esc: omitir", initials),
+            None => match &self.next {
+                Some(next) => format!(
+                    "Siguiente nivel: {} \n Enter: continuar   R: jugar otra vez   Esc: volver al menu",
+                    LevelComplete::level_name(app, next),
+                ),
+                None => "Completaste todos los niveles \n Enter: volver al menu   R: jugar otra vez".to_string(),
+            },
+        };
+        framebuffer.drawtext(&prompt, 10, y, FontId::Nasa, Scale::uniform(24.0), 0xFFFFFF);
+    }
+}
+
+// Tiempo de una partida como minutos:segundos.décimas
+pub fn format_time(ticks: u64) -> String {
+    let tenths = ticks * 10 / TICK_RATE as u64;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+// Pantalla al perder la partida
+pub struct GameOver {
+    maze_file: String,
+}
+
+impl GameOver {
+    pub fn new(app: &mut App, maze_file: &str) -> GameOver {
+        app.audio.stop_world_sounds();
+        app.music.set_ducked(false);
+        app.music.play(app.audio.as_mut(), app.menu_music);
+        GameOver { maze_file: maze_file.to_string() }
+    }
+}
+
+impl Scene for GameOver {
+    fn update(&mut self, app: &mut App, input: &InputState, _dt: Duration) -> Transition {
+        if input.is_released(Key::Enter) || input.is_pressed(Key::R) {
+            return Transition::Replace(Box::new(Playing::new(app, &self.maze_file)));
+        }
+        if input.is_pressed(Key::Escape) {
+            return Transition::Reset(Box::new(Menu::new(app)));
+        }
+        Transition::None
+    }
+
+    fn render(&self, _app: &App, framebuffer: &mut Framebuffer) {
+        framebuffer.clear();
+        let center_x = framebuffer.width as i32 / 2;
+        let center_y = framebuffer.height as i32 / 2;
+        let title = TextStyle::new(FontId::Sixty, 64.0, 0xFF0000).align(Align::Center, Align::End).outline(0x400000);
+        framebuffer.draw_text("PERDISTE", center_x, center_y, &title);
+        let help = TextStyle::new(FontId::Nasa, 24.0, 0xFFFFFF).align(Align::Center, Align::Start);
+        framebuffer.draw_text("Enter: intentar de nuevo\nEsc: volver al menu", center_x, center_y + 30, &help);
+    }
+}
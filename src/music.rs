@@ -0,0 +1,127 @@
+use crate::audio::{AudioBackend, TrackId};
+
+// Segundos que tarda un cruce entre dos pistas
+const CROSSFADE_TIME: f32 = 2.0;
+// Volumen de la música mientras está atenuada (sustos, pausa)
+const DUCKED_VOLUME: f32 = 0.3;
+// Segundos que tarda en bajar o recuperarse la atenuación
+const DUCK_TIME: f32 = 0.4;
+
+struct Track {
+    id: TrackId,
+    sound_id: String,
+    fade: f32,
+    fading_out: bool,
+}
+
+// Controla la música de fondo: cambia de pista con un cruce suave y la baja
+// durante los sustos y la pausa
+pub struct MusicController {
+    tracks: Vec<Track>,
+    duck: f32,
+    ducked: bool,
+}
+
+impl MusicController {
+    pub fn new() -> Self {
+        MusicController { tracks: Vec::new(), duck: 1.0, ducked: false }
+    }
+
+    // Cambia a otra pista. Si ya está sonando, sigue sin cortarse.
+    pub fn play(&mut self, audio: &mut dyn AudioBackend, sound_id: &str) {
+        if let Some(track) = self.tracks.iter_mut().find(|track| track.sound_id == sound_id) {
+            track.fading_out = false;
+        } else if let Some(id) = audio.start_track(sound_id) {
+            self.tracks.push(Track { id, sound_id: sound_id.to_string(), fade: 0.0, fading_out: false });
+        }
+
+        for track in &mut self.tracks {
+            if track.sound_id != sound_id {
+                track.fading_out = true;
+            }
+        }
+    }
+
+    pub fn set_ducked(&mut self, ducked: bool) {
+        self.ducked = ducked;
+    }
+
+    // Avanza los cruces y la atenuación; se llama una vez por cuadro
+    pub fn update(&mut self, audio: &mut dyn AudioBackend, dt: f32) {
+        let duck_target = if self.ducked { DUCKED_VOLUME } else { 1.0 };
+        let duck_step = (1.0 - DUCKED_VOLUME) * dt / DUCK_TIME;
+        self.duck = approach(self.duck, duck_target, duck_step);
+
+        let fade_step = dt / CROSSFADE_TIME;
+        for track in &mut self.tracks {
+            let target = if track.fading_out { 0.0 } else { 1.0 };
+            track.fade = approach(track.fade, target, fade_step);
+            audio.set_track_volume(track.id, track.fade * self.duck);
+            if track.fading_out && track.fade == 0.0 {
+                audio.stop_track(track.id);
+            }
+        }
+        self.tracks.retain(|track| !(track.fading_out && track.fade == 0.0));
+    }
+
+    // Corta toda la música, al salir del juego
+    pub fn stop(&mut self, audio: &mut dyn AudioBackend) {
+        for track in self.tracks.drain(..) {
+            audio.stop_track(track.id);
+        }
+    }
+}
+
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    if value < target {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::RecordingBackend;
+
+    #[test]
+    fn test_crossfade_replaces_track() {
+        let mut audio = RecordingBackend::new();
+        let mut music = MusicController::new();
+        music.play(&mut audio, "menu");
+        music.update(&mut audio, CROSSFADE_TIME);
+        assert_eq!(audio.tracks[0], ("menu".to_string(), 1.0, false));
+
+        music.play(&mut audio, "nivel");
+        music.update(&mut audio, CROSSFADE_TIME / 2.0);
+        assert!(audio.tracks[0].1 > 0.0 && audio.tracks[1].1 > 0.0);
+
+        music.update(&mut audio, CROSSFADE_TIME / 2.0);
+        assert!(audio.tracks[0].2);
+        assert_eq!(audio.tracks[1], ("nivel".to_string(), 1.0, false));
+    }
+
+    #[test]
+    fn test_same_track_keeps_playing() {
+        let mut audio = RecordingBackend::new();
+        let mut music = MusicController::new();
+        music.play(&mut audio, "musica");
+        music.play(&mut audio, "musica");
+        assert_eq!(audio.tracks.len(), 1);
+    }
+
+    #[test]
+    fn test_ducking() {
+        let mut audio = RecordingBackend::new();
+        let mut music = MusicController::new();
+        music.play(&mut audio, "musica");
+        music.set_ducked(true);
+        music.update(&mut audio, CROSSFADE_TIME);
+        assert!((audio.tracks[0].1 - DUCKED_VOLUME).abs() < 1e-6);
+
+        music.set_ducked(false);
+        music.update(&mut audio, DUCK_TIME);
+        assert_eq!(audio.tracks[0].1, 1.0);
+    }
+}
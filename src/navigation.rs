@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+use nalgebra_glm::Vec2;
+
+pub type Cell = (usize, usize); // columna, fila
+
+// Las paredes son los únicos caracteres que no se pueden atravesar
+pub fn is_walkable(maze: &[Vec<char>], (col, row): Cell) -> bool {
+    match maze.get(row).and_then(|line| line.get(col)) {
+        Some('+' | '-' | '|') | None => false,
+        Some(_) => true,
+    }
+}
+
+pub fn cell_at(pos: Vec2, block_size: usize) -> Cell {
+    (pos.x.max(0.0) as usize / block_size, pos.y.max(0.0) as usize / block_size)
+}
+
+// Camino más corto entre dos celdas, sin incluir la de partida. Búsqueda en
+// anchura: todos los pasos cuestan lo mismo y los laberintos son pequeños.
+pub fn find_path(maze: &[Vec<char>], from: Cell, to: Cell) -> Option<Vec<Cell>> {
+    if !is_walkable(maze, from) || !is_walkable(maze, to) {
+        return None;
+    }
+    let width = maze.iter().map(|line| line.len()).max().unwrap_or(0);
+    let index = |(col, row): Cell| row * width + col;
+    let mut came_from: Vec<Option<Cell>> = vec![None; width * maze.len()];
+    let mut queue = VecDeque::from([from]);
+    came_from[index(from)] = Some(from);
+
+    while let Some(cell) = queue.pop_front() {
+        if cell == to {
+            let mut path = vec![to];
+            let mut current = to;
+            while let Some(previous) = came_from[index(current)].filter(|&previous| previous != current) {
+                if previous == from {
+                    break;
+                }
+                path.push(previous);
+                current = previous;
+            }
+            path.reverse();
+            if from == to {
+                path.clear();
+            }
+            return Some(path);
+        }
+
+        let (col, row) = cell;
+        let neighbours = [
+            (col + 1, row),
+            (col, row + 1),
+            (col.wrapping_sub(1), row),
+            (col, row.wrapping_sub(1)),
+        ];
+        for next in neighbours {
+            if is_walkable(maze, next) && came_from[index(next)].is_none() {
+                came_from[index(next)] = Some(cell);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+// Hay línea de vista si ninguna pared corta el segmento entre los dos puntos
+pub fn line_of_sight(maze: &[Vec<char>], from: Vec2, to: Vec2, block_size: usize) -> bool {
+    let delta = to - from;
+    let steps = (delta.norm() / (block_size as f32 / 5.0)).ceil().max(1.0) as usize;
+    (0..=steps).all(|step| {
+        let pos = from + delta * (step as f32 / steps as f32);
+        is_walkable(maze, cell_at(pos, block_size))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn maze() -> Vec<Vec<char>> {
+        vec![
+            "+--+--+".chars().collect(),
+            "|  |  |".chars().collect(),
+            "|     |".chars().collect(),
+            "+--+--+".chars().collect(),
+        ]
+    }
+
+    #[test]
+    fn test_path_goes_around_walls() {
+        let maze = maze();
+        let path = find_path(&maze, (1, 1), (4, 1)).unwrap();
+        assert_eq!(path.last(), Some(&(4, 1)));
+        assert_eq!(path.len(), 5);
+        assert!(path.iter().all(|&cell| is_walkable(&maze, cell)));
+        // Cada paso avanza una sola celda
+        let mut previous = (1, 1);
+        for &(col, row) in &path {
+            assert_eq!(col.abs_diff(previous.0) + row.abs_diff(previous.1), 1);
+            previous = (col, row);
+        }
+
+        assert_eq!(find_path(&maze, (1, 1), (1, 1)), Some(vec![]));
+        assert_eq!(find_path(&maze, (1, 1), (3, 1)), None);
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        let maze = maze();
+        assert!(line_of_sight(&maze, Vec2::new(75.0, 125.0), Vec2::new(275.0, 125.0), 50));
+        assert!(!line_of_sight(&maze, Vec2::new(75.0, 75.0), Vec2::new(275.0, 75.0), 50));
+    }
+}
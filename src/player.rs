@@ -5,6 +5,10 @@ pub struct Player {
     pub a: f32,
     pub fov: f32, // fiel of view
     pub previous_mouse_pos: Vec2,
+    pub stamina: f32, // 0.0 a 1.0, se gasta al correr
+    pub sensitivity: f32, // Multiplica el giro con el mouse
+    pub health: f32, // 0.0 a 1.0, los enemigos la quitan; en 0.0 se pierde
+    pub sanity: f32, // 0.0 a 1.0, la quitan el miedo y la oscuridad
 }
 
 impl Player {
@@ -14,6 +18,10 @@ impl Player {
             a: angle,
             fov,
             previous_mouse_pos: Vec2::new(0.0, 0.0),
+            stamina: 1.0,
+            sensitivity: 1.0,
+            health: 1.0,
+            sanity: 1.0,
         }
     }
 }
\ No newline at end of file
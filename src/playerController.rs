@@ -1,10 +1,8 @@
-use minifb::{Key, Window};
+use minifb::Key;
 use nalgebra_glm::{Vec2};
 use std::f32::consts::PI;
-use rodio::{OutputStream, Sink};
-use std::io::BufReader;
-use std::fs::File;
-use crate::{Player, load_maze};
+use crate::Player;
+use crate::input::InputState;
 
 fn is_wall_at(maze: &Vec<Vec<char>>, pos: Vec2, block_size: usize) -> bool {
     let x = pos.x as usize;
@@ -22,57 +20,73 @@ fn is_wall_at(maze: &Vec<Vec<char>>, pos: Vec2, block_size: usize) -> bool {
     maze[j][i] == '+' || maze[j][i] == '-' || maze[j][i] == '|'
 }
 
-pub fn process_events(window: &Window, player: &mut Player, maze_file: &str) {
+// Resultado del movimiento de un tick
+pub struct Movement {
+    pub distance: f32,
+    pub sprinting: bool,
+}
+
+// Mueve al jugador según la entrada
+pub fn process_events(input: &InputState, player: &mut Player, maze: &Vec<Vec<char>>) -> Movement {
     const MOVE_SPEED: f32 = 5.0; // Reducido para movimiento más lento
+    const SPRINT_MULTIPLIER: f32 = 1.6;
+    const STAMINA_DRAIN: f32 = 1.0 / 240.0; // Cuatro segundos corriendo
+    const STAMINA_REGEN: f32 = 1.0 / 360.0; // Seis segundos para recuperarse
     const ROTATION_SPEED: f32 = PI / 50.0; // Reducido para rotación más lenta
 
-    // Cargar el laberinto
-    let maze = load_maze(maze_file);
     let block_size = 50; // Tamaño de cada bloque en píxeles
 
     // Obtener la posición del mouse
-    if let Some((mouse_x, _mouse_y)) = window.get_mouse_pos(minifb::MouseMode::Clamp) {
+    if let Some((mouse_x, _mouse_y)) = input.mouse_pos {
         let mouse_x = mouse_x as f32;
         let delta_x = mouse_x - player.previous_mouse_pos.x;
         if delta_x.abs() > 0.1 {  // Consider a significant movement
-            player.a += delta_x.signum() * ROTATION_SPEED;
+            player.a += delta_x.signum() * ROTATION_SPEED * player.sensitivity;
         }
         player.previous_mouse_pos.x = mouse_x;
     }
 
     let mut new_pos = player.pos;
-
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let sink = Sink::try_new(&stream_handle).unwrap();
-    let file = File::open("./pasos.mp3").unwrap(); // replace with your sound file path
-    let source = rodio::Decoder::new(BufReader::new(file)).unwrap();
-    sink.append(source);
-    sink.set_volume(10.0); // adjust volume as needed
+    let start_pos = player.pos;
 
     // Procesar rotación
-    if window.is_key_down(Key::Left) || window.is_key_down(Key::A) {
+    if input.is_held(Key::Left) || input.is_held(Key::A) {
         player.a -= ROTATION_SPEED; // Girar el punto de vista a la izquierda
     }
-    if window.is_key_down(Key::Right) || window.is_key_down(Key::D) {
+    if input.is_held(Key::Right) || input.is_held(Key::D) {
         player.a += ROTATION_SPEED; // Girar el punto de vista a la derecha
     }
 
+    // Correr con Shift mientras quede energía
+    let wants_to_move = input.is_held(Key::Up) || input.is_held(Key::W)
+        || input.is_held(Key::Down) || input.is_held(Key::S);
+    let sprinting = wants_to_move && input.is_held(Key::LeftShift) && player.stamina > 0.0;
+    if sprinting {
+        player.stamina = (player.stamina - STAMINA_DRAIN).max(0.0);
+    } else {
+        player.stamina = (player.stamina + STAMINA_REGEN).min(1.0);
+    }
+    let speed = if sprinting { MOVE_SPEED * SPRINT_MULTIPLIER } else { MOVE_SPEED };
+
     // Calcular el vector de movimiento
-    let move_vec = Vec2::new(player.a.cos(), player.a.sin()) * MOVE_SPEED;
+    let move_vec = Vec2::new(player.a.cos(), player.a.sin()) * speed;
 
     // Procesar movimiento
-    if window.is_key_down(Key::Up) || window.is_key_down(Key::W) {
+    if input.is_held(Key::Up) || input.is_held(Key::W) {
         new_pos = player.pos + move_vec;
-        if !is_wall_at(&maze, new_pos, block_size) {
+        if !is_wall_at(maze, new_pos, block_size) {
             player.pos = new_pos;
-            sink.play(); // play sound
         }
     }
-    if window.is_key_down(Key::Down) || window.is_key_down(Key::S) {
+    if input.is_held(Key::Down) || input.is_held(Key::S) {
         new_pos = player.pos - move_vec;
-        if !is_wall_at(&maze, new_pos, block_size) {
+        if !is_wall_at(maze, new_pos, block_size) {
             player.pos = new_pos;
-            sink.play(); // play sound
         }
     }
+
+    Movement {
+        distance: (player.pos - start_pos).norm(),
+        sprinting,
+    }
 }
\ No newline at end of file
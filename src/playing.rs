@@ -0,0 +1,349 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+use minifb::Key;
+use nalgebra_glm::Vec2;
+use rusttype::Scale;
+use crate::audio::play_game_event;
+use crate::campaign::PROGRESS_FILE;
+use crate::effects::ScreenEffects;
+use crate::postprocess::{Lut, PostEffect};
+use crate::fonts::FontId;
+use crate::text::{Span, TextStyle};
+use crate::framebuffer::{Framebuffer, LayerId};
+use crate::game::{Game, GameEvent, TICK_RATE};
+use crate::input::InputState;
+use crate::menu::{format_time, GameOver, ItemList, LevelComplete, Menu, SettingsMenu};
+use crate::positional::spatialize;
+use crate::replay::{FrameInput, Replay};
+use crate::rng::Rng;
+use crate::savegame::{slot_path, SaveGame, SLOTS};
+use crate::score::Leaderboard;
+use crate::scene::{App, Scene, Transition};
+use crate::{print_replay_summary, render2D, render3D, render_fps, render_jumpscare, render_minimap, start_level_sounds};
+
+// Una partida en curso
+pub struct Playing {
+    game: Game,
+    mode: &'static str,
+    tick_input: InputState,
+    accumulator: Duration,
+    recording: Option<Replay>,
+    replay_frames: Option<std::vec::IntoIter<FrameInput>>,
+    pause_choice: Rc<Cell<Option<PauseChoice>>>,
+    atmosphere: Vec<PostEffect>, // Efectos de pantalla del nivel
+}
+
+impl Playing {
+    pub fn new(app: &mut App, maze_file: &str) -> Playing {
+        let seed = app.seed.unwrap_or_else(Rng::seed_from_time);
+        let mut game = Game::new(maze_file, seed);
+        game.player.sensitivity = app.settings.sensitivity;
+        let recording = app.record_path.as_ref().map(|_| {
+            let mut recording = Replay::new(seed, maze_file);
+            recording.sensitivity = game.player.sensitivity;
+            recording
+        });
+        Playing::start(app, game, recording, None)
+    }
+
+    // Reproduce una partida grabada en lugar de leer el teclado
+    pub fn from_replay(app: &mut App, replay: Replay) -> Playing {
+        let mut game = Game::new(&replay.level, replay.seed);
+        game.player.sensitivity = replay.sensitivity;
+        Playing::start(app, game, None, Some(replay.frames.into_iter()))
+    }
+
+    // Continúa una partida guardada. No se graba: la grabación necesitaría
+    // empezar desde el inicio del nivel.
+    pub fn resume(app: &mut App, game: Game) -> Playing {
+        Playing::start(app, game, None, None)
+    }
+
+    fn start(
+        app: &mut App,
+        mut game: Game,
+        recording: Option<Replay>,
+        replay_frames: Option<std::vec::IntoIter<FrameInput>>,
+    ) -> Playing {
+        game.player.fov = app.settings.fov.to_radians();
+        start_level_sounds(app.audio.as_mut(), &mut app.music, &game.level, app.block_size);
+
+        // La tabla de color va primero, antes de que los demás efectos cambien la imagen
+        let mut atmosphere = Vec::new();
+        if let Some(file) = &game.level.color_grade {
+            match Lut::load(file) {
+                Ok(lut) => atmosphere.push(PostEffect::ColorGrade(Rc::new(lut))),
+                Err(e) => eprintln!("No se pudo leer la tabla de color {}: {}", file, e),
+            }
+        }
+        atmosphere.extend(game.level.effects.iter().cloned());
+
+        Playing {
+            game,
+            mode: "3D",
+            tick_input: InputState::new(),
+            accumulator: Duration::ZERO,
+            recording,
+            replay_frames,
+            pause_choice: Rc::new(Cell::new(None)),
+            atmosphere,
+        }
+    }
+
+    // Celdas de los objetos que faltan recoger
+    fn remaining_collectibles(&self) -> Vec<(usize, usize)> {
+        self.game.level.collectibles
+            .iter()
+            .zip(&self.game.collected)
+            .filter(|(_, &collected)| !collected)
+            .map(|(item, _)| item.cell)
+            .collect()
+    }
+
+    fn enemy_positions(&self) -> Vec<Vec2> {
+        self.game.enemies.iter().map(|enemy| enemy.pos).collect()
+    }
+
+    // Al dejar el nivel se guarda la grabación y se cortan los sonidos del nivel
+    fn finish(&mut self, app: &mut App) {
+        app.audio.stop_world_sounds();
+        if let (Some(recording), Some(record_path)) = (&self.recording, &app.record_path) {
+            match recording.save(record_path) {
+                Ok(()) => println!("Repetición guardada en {}", record_path),
+                Err(e) => eprintln!("No se pudo guardar la repetición {}: {}", record_path, e),
+            }
+        }
+        if self.replay_frames.is_some() {
+            print_replay_summary(&self.game);
+        }
+    }
+}
+
+impl Scene for Playing {
+    fn update(&mut self, app: &mut App, input: &InputState, dt: Duration) -> Transition {
+        // Lo que se eligió en el menú de pausa al cerrarse
+        match self.pause_choice.take() {
+            Some(PauseChoice::Restart) => {
+                self.finish(app);
+                let maze_file = self.game.maze_file.clone();
+                return Transition::Replace(Box::new(Playing::new(app, &maze_file)));
+            }
+            Some(PauseChoice::QuitToMenu) => {
+                self.finish(app);
+                return Transition::Reset(Box::new(Menu::new(app)));
+            }
+            None => {}
+        }
+
+        if input.is_pressed(Key::M) {
+            self.mode = if self.mode == "2D" { "3D" } else { "2D" };
+        }
+
+        // Pausa: congela la simulación, los sustos y los efectos
+        if input.is_pressed(Key::P) || input.is_pressed(Key::Escape) {
+            app.audio.set_paused(true);
+            let paused = Paused::new(self.game.to_save(), Rc::clone(&self.pause_choice), app.screen_size);
+            return Transition::Push(Box::new(paused));
+        }
+
+        if self.recording.is_none() && self.replay_frames.is_none() {
+            self.game.player.fov = app.settings.fov.to_radians();
+            // Mientras se graba, la sensibilidad queda como al empezar para que la
+            // repetición sea exacta
+            self.game.player.sensitivity = app.settings.sensitivity;
+        }
+
+        // Avanzar la simulación a paso fijo. No intentar recuperar más de unos
+        // cuantos ticks si un cuadro tardó demasiado.
+        let tick_duration = Duration::new(1, 0) / TICK_RATE;
+        self.accumulator = (self.accumulator + dt).min(tick_duration * 5);
+
+        while self.accumulator >= tick_duration {
+            self.accumulator -= tick_duration;
+
+            let frame = match self.replay_frames.as_mut() {
+                Some(frames) => match frames.next() {
+                    Some(frame) => frame,
+                    None => {
+                        self.finish(app);
+                        return Transition::Quit;
+                    }
+                },
+                None => FrameInput::capture(input),
+            };
+            if let Some(recording) = self.recording.as_mut() {
+                recording.push(frame);
+            }
+            frame.apply(&mut self.tick_input);
+
+            for event in self.game.update(&self.tick_input) {
+                play_game_event(app.audio.as_mut(), &event);
+                if event == GameEvent::Died {
+                    self.finish(app);
+                    return Transition::Replace(Box::new(GameOver::new(app, &self.game.maze_file)));
+                }
+                if event == GameEvent::ReachedGoal {
+                    self.finish(app);
+                    // Una repetición no cuenta como progreso
+                    if self.replay_frames.is_none() {
+                        app.progress.complete(&self.game.maze_file);
+                        if let Err(e) = app.progress.save(PROGRESS_FILE) {
+                            eprintln!("No se pudo guardar el progreso en {}: {}", PROGRESS_FILE, e);
+                        }
+                    }
+                    // Las repeticiones no entran en la tabla de puntajes
+                    let table = self.replay_frames.is_none()
+                        .then(|| Leaderboard::table_name(&self.game.maze_file, app.seed));
+                    return Transition::Replace(Box::new(LevelComplete::new(app, &self.game, table)));
+                }
+            }
+        }
+
+        let game = &self.game;
+        app.audio.update_listener(&|pos| spatialize(&game.player, &game.maze, pos, app.block_size));
+        app.music.set_ducked(game.show_jumpscare);
+        Transition::None
+    }
+
+    fn render(&self, app: &App, framebuffer: &mut Framebuffer) {
+        framebuffer.clear();
+        let collectibles = self.remaining_collectibles();
+        let enemies = self.enemy_positions();
+        if self.mode == "2D" {
+            render2D(framebuffer, &self.game.player, &self.game.maze_file, &collectibles, &enemies);
+        } else {
+            let mut effects = ScreenEffects::of(&self.game);
+            let atmosphere: &[PostEffect] = if app.settings.post_effects { &self.atmosphere } else { &[] };
+            if !app.settings.post_effects {
+                effects.shake = (0.0, 0.0);
+            }
+            render3D(framebuffer, &self.game.player, &self.game.maze_file, &app.settings, &enemies, &effects);
+            framebuffer.set_post_process(effects.post_process(atmosphere, self.game.tick));
+        }
+
+        // El HUD se ve completo salvo que la pausa lo atenúe
+        framebuffer.set_layer(LayerId::Hud);
+        framebuffer.set_layer_opacity(LayerId::Hud, 1.0);
+        render_minimap(framebuffer, &self.game.maze, app.block_size, &self.game.player, &collectibles, &enemies);
+
+        // Renderizar el enemigo si es el momento adecuado
+        if self.game.show_jumpscare {
+            framebuffer.set_layer(LayerId::Overlay);
+            render_jumpscare(framebuffer, &self.game.player, app.block_size, self.game.scare_variant, self.game.jumpscare_fade());
+        }
+
+        framebuffer.set_layer(LayerId::Debug);
+        render_fps(framebuffer, app.fps);
+        framebuffer.set_layer(LayerId::Hud);
+
+        // Tiempo del nivel y objetos recogidos
+        let collected = self.game.collected.iter().filter(|&&collected| collected).count();
+        let time = format_time(self.game.tick);
+        let items = format!("{}/{}", collected, self.game.collected.len());
+        let mut spans = vec![Span { text: "Tiempo ", color: 0xAAAAAA }, Span { text: &time, color: 0xFFFFFF }];
+        if !self.game.collected.is_empty() {
+            spans.push(Span { text: "   Objetos ", color: 0xAAAAAA });
+            spans.push(Span { text: &items, color: 0xFFCC00 });
+        }
+        let hud_x = framebuffer.width.saturating_sub(400);
+        // Panel translúcido detrás del texto para que se lea sobre cualquier pared
+        framebuffer.set_opacity(0.5);
+        framebuffer.fill_rect(hud_x as i32 - 10, 5, 330, 85, 0x000000);
+        framebuffer.set_opacity(1.0);
+        framebuffer.draw_spans(&spans, hud_x as i32, 10, &TextStyle::new(FontId::Nasa, 20.0, 0xFFFFFF).shadow(0x000000));
+        draw_bar(framebuffer, hud_x, 40, "Salud", self.game.player.health, 0xCC2222);
+        draw_bar(framebuffer, hud_x, 64, "Cordura", self.game.player.sanity, 0x6666FF);
+    }
+}
+
+// Barra con su nombre, llena según un valor de 0.0 a 1.0
+fn draw_bar(framebuffer: &mut Framebuffer, x: usize, y: usize, label: &str, value: f32, color: u32) {
+    let (bar_x, width, height) = (x as i32 + 100, 200, 14);
+    framebuffer.drawtext(label, x, y, FontId::Nasa, Scale::uniform(18.0), 0xFFFFFF);
+    let filled = (value.clamp(0.0, 1.0) * width as f32) as usize;
+    framebuffer.fill_rect(bar_x, y as i32 + 2, width, height, 0x333333);
+    framebuffer.fill_rect(bar_x, y as i32 + 2, filled, height, color);
+    framebuffer.rect(bar_x - 1, y as i32 + 1, width + 2, height + 2, 0x888888);
+}
+
+const SLOT_KEYS: [Key; SLOTS] = [Key::Key1, Key::Key2, Key::Key3];
+
+const PAUSE_ITEMS: [&str; 4] = ["Continuar", "Reiniciar nivel", "Opciones", "Salir al menu"];
+
+// Lo que la partida debe hacer al cerrarse la pausa, además de continuar
+#[derive(Clone, Copy)]
+enum PauseChoice {
+    Restart,
+    QuitToMenu,
+}
+
+// Menú de pausa encima de la partida, que queda congelada debajo. Como la
+// partida no avanza mientras tanto, el estado para guardar se toma al pausar.
+pub struct Paused {
+    save: SaveGame,
+    choice: Rc<Cell<Option<PauseChoice>>>,
+    list: ItemList,
+    message: String,
+}
+
+impl Paused {
+    fn new(save: SaveGame, choice: Rc<Cell<Option<PauseChoice>>>, screen_size: (usize, usize)) -> Paused {
+        // En pantallas muy chicas el menú queda pegado al borde
+        let list = ItemList::new(PAUSE_ITEMS.len(), (screen_size.0 / 2).saturating_sub(140), (screen_size.1 / 2).saturating_sub(60), 36);
+        Paused { save, choice, list, message: String::new() }
+    }
+
+    fn close(&self, app: &mut App, choice: Option<PauseChoice>) -> Transition {
+        app.audio.set_paused(false);
+        self.choice.set(choice);
+        Transition::Pop
+    }
+}
+
+impl Scene for Paused {
+    fn update(&mut self, app: &mut App, input: &InputState, _dt: Duration) -> Transition {
+        app.music.set_ducked(true);
+        if input.is_pressed(Key::P) || input.is_pressed(Key::Escape) {
+            return self.close(app, None);
+        }
+        for (slot, key) in SLOT_KEYS.iter().enumerate() {
+            if input.is_pressed(*key) {
+                self.message = match self.save.save(&slot_path(slot)) {
+                    Ok(()) => format!("Partida guardada en la ranura {}", slot + 1),
+                    Err(e) => format!("No se pudo guardar: {}", e),
+                };
+            }
+        }
+
+        match self.list.update(app, input) {
+            Some(0) => self.close(app, None),
+            Some(1) => self.close(app, Some(PauseChoice::Restart)),
+            Some(2) => Transition::Push(Box::new(SettingsMenu::new())),
+            Some(_) => self.close(app, Some(PauseChoice::QuitToMenu)),
+            None => Transition::None,
+        }
+    }
+
+    fn render(&self, _app: &App, framebuffer: &mut Framebuffer) {
+        // Oscurecer la partida que queda detrás del menú
+        framebuffer.set_layer(LayerId::Overlay);
+        framebuffer.set_layer_opacity(LayerId::Hud, 0.3);
+        framebuffer.set_opacity(0.5);
+        framebuffer.fill_rect(0, 0, framebuffer.width, framebuffer.height, 0x000000);
+        framebuffer.set_opacity(1.0);
+
+        let x = (framebuffer.width / 2).saturating_sub(160);
+        let y = (framebuffer.height / 2).saturating_sub(120);
+        framebuffer.drawtext("PAUSA", x, y, FontId::Sixty, Scale::uniform(40.0), 0xFFFFFF);
+        let items: Vec<String> = PAUSE_ITEMS.iter().map(|item| item.to_string()).collect();
+        self.list.render(framebuffer, &items, 28.0);
+        let help_y = framebuffer.height / 2 + 100;
+        framebuffer.drawtext("1, 2, 3: guardar partida", x, help_y, FontId::Nasa, Scale::uniform(20.0), 0xAAAAAA);
+        framebuffer.drawtext(&self.message, x, help_y + 30, FontId::Nasa, Scale::uniform(20.0), 0xFFFF00);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
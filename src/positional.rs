@@ -0,0 +1,177 @@
+use nalgebra_glm::Vec2;
+use rodio::Source;
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::player::Player;
+use crate::cast_ray;
+
+// Distancia a partir de la cual un sonido del mundo ya no se oye
+const MAX_DISTANCE: f32 = 600.0;
+
+// Cómo debe oírse un sonido desde la posición del jugador
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Spatial {
+    pub gain: f32,
+    pub pan: f32, // -1.0 izquierda, 1.0 derecha
+    pub occluded: bool,
+}
+
+// Calcula volumen, paneo y oclusión de un sonido en `source_pos`
+pub fn spatialize(player: &Player, maze: &Vec<Vec<char>>, source_pos: Vec2, block_size: usize) -> Spatial {
+    let delta = source_pos - player.pos;
+    let distance = delta.norm();
+    let falloff = (1.0 - distance / MAX_DISTANCE).clamp(0.0, 1.0);
+
+    // Ángulo del sonido relativo a hacia dónde mira el jugador
+    let angle = delta.y.atan2(delta.x);
+    let mut relative = angle - player.a;
+    while relative > PI {
+        relative -= 2.0 * PI;
+    }
+    while relative < -PI {
+        relative += 2.0 * PI;
+    }
+
+    // Si un rayo hacia la fuente choca antes de llegar, hay una pared en medio
+    let ray = cast_ray(None, maze, player, angle, block_size);
+    let occluded = ray.intersect.distance + block_size as f32 / 2.0 < distance;
+
+    Spatial {
+        gain: falloff * falloff * if occluded { 0.5 } else { 1.0 },
+        pan: relative.sin(),
+        occluded,
+    }
+}
+
+// Parámetros compartidos entre el hilo del juego y el de audio
+pub struct PositionalParams {
+    left: AtomicU32,
+    right: AtomicU32,
+    filter: AtomicU32,
+}
+
+impl PositionalParams {
+    pub fn new() -> Arc<PositionalParams> {
+        Arc::new(PositionalParams {
+            left: AtomicU32::new(0f32.to_bits()),
+            right: AtomicU32::new(0f32.to_bits()),
+            filter: AtomicU32::new(1f32.to_bits()),
+        })
+    }
+
+    pub fn set(&self, spatial: Spatial) {
+        // Paneo de potencia constante
+        let angle = (spatial.pan.clamp(-1.0, 1.0) + 1.0) * PI / 4.0;
+        self.left.store((spatial.gain * angle.cos()).to_bits(), Ordering::Relaxed);
+        self.right.store((spatial.gain * angle.sin()).to_bits(), Ordering::Relaxed);
+        // Un filtro pasa bajos apagado suena como si viniera detrás de una pared
+        let filter: f32 = if spatial.occluded { 0.12 } else { 1.0 };
+        self.filter.store(filter.to_bits(), Ordering::Relaxed);
+    }
+
+    fn get(value: &AtomicU32) -> f32 {
+        f32::from_bits(value.load(Ordering::Relaxed))
+    }
+}
+
+// Fuente que aplica paneo, volumen y filtro leyendo los parámetros en cada muestra.
+// Los cambios se suavizan para que no truenen al moverse el jugador.
+pub struct PositionalSource<S> {
+    inner: S,
+    params: Arc<PositionalParams>,
+    channel: usize,
+    gain: [f32; 2],
+    filter: f32,
+    filtered: [f32; 2],
+}
+
+impl<S> PositionalSource<S> {
+    pub fn new(inner: S, params: Arc<PositionalParams>) -> Self {
+        PositionalSource { inner, params, channel: 0, gain: [0.0; 2], filter: 1.0, filtered: [0.0; 2] }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for PositionalSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        let channels = self.inner.channels().max(1) as usize;
+        let side = if channels == 1 { 0 } else { self.channel % 2 };
+        self.channel = (self.channel + 1) % channels;
+
+        let target = if channels == 1 {
+            (PositionalParams::get(&self.params.left) + PositionalParams::get(&self.params.right)) / 2.0
+        } else if side == 0 {
+            PositionalParams::get(&self.params.left)
+        } else {
+            PositionalParams::get(&self.params.right)
+        };
+        self.gain[side] += (target - self.gain[side]) * 0.002;
+        self.filter += (PositionalParams::get(&self.params.filter) - self.filter) * 0.002;
+
+        self.filtered[side] += self.filter * (sample - self.filtered[side]);
+        Some(self.filtered[side] * self.gain[side])
+    }
+}
+
+impl<S: Source<Item = f32>> Source for PositionalSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_maze() -> Vec<Vec<char>> {
+        vec![
+            "+--+--+--+".chars().collect(),
+            "|        |".chars().collect(),
+            "|        |".chars().collect(),
+            "|        |".chars().collect(),
+            "+--+--+--+".chars().collect(),
+        ]
+    }
+
+    #[test]
+    fn test_pan_follows_facing() {
+        let maze = open_maze();
+        // Mirando hacia +x, un sonido en +y queda a la derecha
+        let player = Player::new(100.0, 100.0, 0.0, PI / 3.0);
+        let right = spatialize(&player, &maze, Vec2::new(100.0, 160.0), 50);
+        let left = spatialize(&player, &maze, Vec2::new(100.0, 60.0), 50);
+        assert!(right.pan > 0.9);
+        assert!(left.pan < -0.9);
+    }
+
+    #[test]
+    fn test_gain_falls_with_distance_and_walls() {
+        let mut maze = open_maze();
+        let player = Player::new(75.0, 75.0, 0.0, PI / 3.0);
+        let near = spatialize(&player, &maze, Vec2::new(150.0, 75.0), 50);
+        let far = spatialize(&player, &maze, Vec2::new(400.0, 75.0), 50);
+        assert!(near.gain > far.gain);
+        assert!(!near.occluded);
+
+        maze[1][5] = '|';
+        let blocked = spatialize(&player, &maze, Vec2::new(400.0, 75.0), 50);
+        assert!(blocked.occluded);
+        assert!(blocked.gain < far.gain);
+    }
+}
@@ -0,0 +1,263 @@
+use std::io;
+use std::rc::Rc;
+use crate::rng::Rng;
+
+// Un efecto que se aplica sobre la imagen ya dibujada del mundo
+#[derive(Clone)]
+pub enum PostEffect {
+    Vignette(f32),            // 0.0 a 1.0, cuánto se oscurecen los bordes
+    Desaturate(f32),          // 0.0 a 1.0, cuánto color se pierde
+    Grain(f32),               // 0.0 a 1.0, ruido que cambia en cada cuadro
+    Scanlines(f32),           // 0.0 a 1.0, cuánto se oscurecen las filas impares
+    ChromaticAberration(f32), // Separación en píxeles del rojo y el azul
+    ColorGrade(Rc<Lut>),
+    Flash(u32, f32),          // Color que tapa la imagen, de 0.0 a 1.0
+}
+
+impl PostEffect {
+    // Efecto de la configuración de un nivel, por nombre y valor
+    pub fn parse(name: &str, value: &str) -> Option<PostEffect> {
+        let amount: f32 = value.parse().ok()?;
+        match name {
+            "vineta" => Some(PostEffect::Vignette(amount.clamp(0.0, 1.0))),
+            "desaturar" => Some(PostEffect::Desaturate(amount.clamp(0.0, 1.0))),
+            "grano" => Some(PostEffect::Grain(amount.clamp(0.0, 1.0))),
+            "lineas" => Some(PostEffect::Scanlines(amount.clamp(0.0, 1.0))),
+            "aberracion" => Some(PostEffect::ChromaticAberration(amount.max(0.0))),
+            _ => None,
+        }
+    }
+}
+
+// Efectos de un cuadro, en orden. La semilla cambia el grano en cada cuadro.
+pub struct PostProcess {
+    pub effects: Vec<PostEffect>,
+    pub seed: u64,
+}
+
+impl PostProcess {
+    pub fn apply(&self, buffer: &mut [u32], width: usize, height: usize) {
+        for effect in &self.effects {
+            match effect {
+                PostEffect::Vignette(amount) => vignette(buffer, width, height, *amount),
+                PostEffect::Desaturate(amount) => {
+                    for pixel in buffer.iter_mut() {
+                        let gray = luminance(*pixel);
+                        *pixel = map_channels(*pixel, |channel| channel + (gray - channel) * amount);
+                    }
+                }
+                PostEffect::Grain(amount) => {
+                    let mut rng = Rng::new(self.seed);
+                    for pixel in buffer.iter_mut() {
+                        let noise = (rng.next_f32() - 0.5) * amount * 255.0;
+                        *pixel = map_channels(*pixel, |channel| channel + noise);
+                    }
+                }
+                PostEffect::Scanlines(amount) => {
+                    for row in buffer.chunks_mut(width).skip(1).step_by(2) {
+                        for pixel in row {
+                            *pixel = map_channels(*pixel, |channel| channel * (1.0 - amount));
+                        }
+                    }
+                }
+                PostEffect::ChromaticAberration(shift) => aberration(buffer, width, shift.round() as usize),
+                PostEffect::ColorGrade(lut) => {
+                    for pixel in buffer.iter_mut() {
+                        *pixel = lut.map(*pixel);
+                    }
+                }
+                PostEffect::Flash(color, amount) => {
+                    let [r, g, b] = channels(*color);
+                    for pixel in buffer.iter_mut() {
+                        let [pr, pg, pb] = channels(*pixel);
+                        let mix = |from: f32, to: f32| from + (to - from) * amount;
+                        *pixel = pack(mix(pr, r), mix(pg, g), mix(pb, b));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Oscurece desde la mitad del radio hacia las esquinas
+fn vignette(buffer: &mut [u32], width: usize, height: usize, amount: f32) {
+    let cx = width as f32 / 2.0;
+    let cy = height as f32 / 2.0;
+    let max_distance = (cx * cx + cy * cy).sqrt();
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+            let edge = (((dx * dx + dy * dy).sqrt() / max_distance - 0.5) * 2.0).max(0.0);
+            let brightness = 1.0 - edge * amount;
+            let pixel = &mut buffer[y * width + x];
+            *pixel = map_channels(*pixel, |channel| channel * brightness);
+        }
+    }
+}
+
+// Corre el rojo hacia la izquierda y el azul hacia la derecha, como un lente barato
+fn aberration(buffer: &mut [u32], width: usize, shift: usize) {
+    if shift == 0 {
+        return;
+    }
+    let mut source = vec![0; width];
+    for row in buffer.chunks_mut(width) {
+        source.copy_from_slice(row);
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let red = source[(x + shift).min(width - 1)] & 0xFF0000;
+            let blue = source[x.saturating_sub(shift)] & 0x0000FF;
+            *pixel = red | (source[x] & 0x00FF00) | blue;
+        }
+    }
+}
+
+fn channels(color: u32) -> [f32; 3] {
+    [((color >> 16) & 0xFF) as f32, ((color >> 8) & 0xFF) as f32, (color & 0xFF) as f32]
+}
+
+fn pack(r: f32, g: f32, b: f32) -> u32 {
+    let channel = |value: f32| value.clamp(0.0, 255.0) as u32;
+    (channel(r) << 16) | (channel(g) << 8) | channel(b)
+}
+
+fn map_channels(color: u32, op: impl Fn(f32) -> f32) -> u32 {
+    let [r, g, b] = channels(color);
+    pack(op(r), op(g), op(b))
+}
+
+fn luminance(color: u32) -> f32 {
+    let [r, g, b] = channels(color);
+    0.299 * r + 0.587 * g + 0.114 * b
+}
+
+// Precisión de la tabla ya interpolada: 64 niveles por canal
+const LUT_BITS: u32 = 6;
+const LUT_SIDE: usize = 1 << LUT_BITS;
+
+// Tabla de color para cambiar el tono de la imagen. Se lee de una imagen de
+// N² x N píxeles: N cuadros de N x N uno al lado del otro, el azul sube de
+// cuadro en cuadro, el rojo hacia la derecha y el verde hacia abajo.
+pub struct Lut {
+    colors: Vec<u32>,
+}
+
+impl Lut {
+    pub fn load(path: &str) -> io::Result<Lut> {
+        let image = image::open(path)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            .to_rgb8();
+        Lut::from_strip(image.width() as usize, image.height() as usize, |x, y| {
+            let [r, g, b] = image.get_pixel(x as u32, y as u32).0;
+            ((r as u32) << 16) | ((g as u32) << 8) | b as u32
+        })
+    }
+
+    // Interpola la tabla de la imagen a 64 niveles por canal, para que después
+    // cada píxel sea una sola búsqueda
+    pub fn from_strip(width: usize, height: usize, pixel: impl Fn(usize, usize) -> u32) -> io::Result<Lut> {
+        let size = height;
+        if size < 2 || width != size * size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("la tabla de color mide {}x{}; debe medir N²xN", width, height),
+            ));
+        }
+        let sample = |r: usize, g: usize, b: usize| channels(pixel(b * size + r, g));
+
+        let mut colors = Vec::with_capacity(LUT_SIDE * LUT_SIDE * LUT_SIDE);
+        let position = |index: usize| {
+            let position = index as f32 / (LUT_SIDE - 1) as f32 * (size - 1) as f32;
+            let low = (position as usize).min(size - 2);
+            (low, position - low as f32)
+        };
+        for r in 0..LUT_SIDE {
+            let (r0, fr) = position(r);
+            for g in 0..LUT_SIDE {
+                let (g0, fg) = position(g);
+                for b in 0..LUT_SIDE {
+                    let (b0, fb) = position(b);
+                    // Interpolación entre los ocho vecinos del cubo
+                    let mut color = [0.0; 3];
+                    for (dr, wr) in [(0, 1.0 - fr), (1, fr)] {
+                        for (dg, wg) in [(0, 1.0 - fg), (1, fg)] {
+                            for (db, wb) in [(0, 1.0 - fb), (1, fb)] {
+                                let weight = wr * wg * wb;
+                                let corner = sample(r0 + dr, g0 + dg, b0 + db);
+                                for channel in 0..3 {
+                                    color[channel] += corner[channel] * weight;
+                                }
+                            }
+                        }
+                    }
+                    colors.push(pack(color[0].round(), color[1].round(), color[2].round()));
+                }
+            }
+        }
+        Ok(Lut { colors })
+    }
+
+    pub fn map(&self, color: u32) -> u32 {
+        let shift = 8 - LUT_BITS;
+        let r = ((color >> 16) & 0xFF) >> shift;
+        let g = ((color >> 8) & 0xFF) >> shift;
+        let b = (color & 0xFF) >> shift;
+        self.colors[((r << (2 * LUT_BITS)) | (g << LUT_BITS) | b) as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effects_change_the_image() {
+        let (width, height) = (10, 10);
+        let run = |effect: PostEffect| {
+            let mut buffer = vec![0xFF0000; width * height];
+            PostProcess { effects: vec![effect], seed: 1 }.apply(&mut buffer, width, height);
+            buffer
+        };
+
+        let gray = run(PostEffect::Desaturate(1.0));
+        let (r, g, b) = (gray[55] >> 16 & 0xFF, gray[55] >> 8 & 0xFF, gray[55] & 0xFF);
+        assert_eq!((r, g), (g, b));
+
+        let vignette = run(PostEffect::Vignette(1.0));
+        assert!(vignette[0] < vignette[55]);
+
+        let lines = run(PostEffect::Scanlines(0.5));
+        assert_eq!(lines[0], 0xFF0000);
+        assert_eq!(lines[width], 0x7F0000);
+
+        let flash = run(PostEffect::Flash(0x0000FF, 1.0));
+        assert!(flash.iter().all(|&pixel| pixel == 0x0000FF));
+
+        // El rojo se corre hacia la izquierda y el azul hacia la derecha
+        let mut buffer: Vec<u32> = (0..width as u32).map(|x| if x < 5 { 0xFF00FF } else { 0 }).collect();
+        aberration(&mut buffer, width, 2);
+        assert_eq!(buffer[2], 0xFF00FF);
+        assert_eq!(buffer[3], 0x0000FF);
+        assert_eq!(buffer[6], 0x0000FF);
+        assert_eq!(buffer[7], 0x000000);
+    }
+
+    #[test]
+    fn test_identity_lut_keeps_colors() {
+        let size = 4;
+        let step = 255 / (size as u32 - 1);
+        let lut = Lut::from_strip(size * size, size, |x, y| {
+            let (r, g, b) = ((x % size) as u32 * step, y as u32 * step, (x / size) as u32 * step);
+            (r << 16) | (g << 8) | b
+        })
+        .unwrap();
+        for color in [0x000000, 0xFFFFFF, 0x804020, 0x12C0F0] {
+            let mapped = lut.map(color);
+            for shift in [16, 8, 0] {
+                let difference = ((mapped >> shift) & 0xFF) as i32 - ((color >> shift) & 0xFF) as i32;
+                assert!(difference.abs() <= 4, "{:06X} -> {:06X}", color, mapped);
+            }
+        }
+
+        assert!(Lut::from_strip(10, 4, |_, _| 0).is_err());
+    }
+}
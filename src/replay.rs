@@ -0,0 +1,216 @@
+use minifb::Key;
+use std::fs;
+use std::io::{self, ErrorKind};
+use crate::input::InputState;
+
+// Teclas que afectan la simulación. El índice de cada tecla es su bit en la
+// máscara grabada, así que solo se pueden agregar teclas al final de la lista.
+const TRACKED_KEYS: [Key; 9] = [
+    Key::W, Key::A, Key::S, Key::D,
+    Key::Up, Key::Left, Key::Down, Key::Right,
+    Key::LeftShift,
+];
+
+const MAGIC: &[u8; 4] = b"RCRP";
+// La versión 1 no guardaba la sensibilidad del mouse; se lee como 1.0
+const VERSION: u8 = 2;
+
+// Entrada de un tick de la simulación
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FrameInput {
+    pub keys: u16,
+    pub mouse_x: Option<f32>,
+}
+
+impl FrameInput {
+    pub fn capture(input: &InputState) -> Self {
+        let mut keys = 0;
+        for (bit, key) in TRACKED_KEYS.iter().enumerate() {
+            if input.is_held(*key) {
+                keys |= 1 << bit;
+            }
+        }
+        FrameInput { keys, mouse_x: input.mouse_pos.map(|(x, _)| x) }
+    }
+
+    // Aplica esta entrada al estado, como si viniera de la ventana
+    pub fn apply(&self, input: &mut InputState) {
+        let keys = TRACKED_KEYS
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| self.keys & (1 << bit) != 0)
+            .map(|(_, key)| *key)
+            .collect();
+        input.update_with(keys, self.mouse_x.map(|x| (x, 0.0)));
+    }
+}
+
+// Grabación completa de una partida: semilla, nivel, sensibilidad del mouse y
+// la entrada de cada tick
+pub struct Replay {
+    pub seed: u64,
+    pub level: String,
+    pub sensitivity: f32,
+    pub frames: Vec<FrameInput>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, level: &str) -> Self {
+        Replay { seed, level: level.to_string(), sensitivity: 1.0, frames: Vec::new() }
+    }
+
+    pub fn push(&mut self, frame: FrameInput) {
+        self.frames.push(frame);
+    }
+
+    pub fn save(&self, file_path: &str) -> io::Result<()> {
+        fs::write(file_path, self.encode())
+    }
+
+    pub fn load(file_path: &str) -> io::Result<Replay> {
+        Replay::decode(&fs::read(file_path)?)
+    }
+
+    // Los ticks iguales consecutivos se guardan como una sola corrida:
+    // repeticiones (u16), teclas (u16) y mouse x (f32, NaN si no hay mouse)
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        out.extend_from_slice(&(self.level.len() as u16).to_le_bytes());
+        out.extend_from_slice(self.level.as_bytes());
+        out.extend_from_slice(&self.sensitivity.to_le_bytes());
+
+        let mut runs: Vec<(u16, FrameInput)> = Vec::new();
+        for frame in &self.frames {
+            match runs.last_mut() {
+                Some((count, last)) if *last == *frame && *count < u16::MAX => *count += 1,
+                _ => runs.push((1, *frame)),
+            }
+        }
+
+        out.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+        for (count, frame) in runs {
+            out.extend_from_slice(&count.to_le_bytes());
+            out.extend_from_slice(&frame.keys.to_le_bytes());
+            out.extend_from_slice(&frame.mouse_x.unwrap_or(f32::NAN).to_le_bytes());
+        }
+        out
+    }
+
+    pub fn decode(data: &[u8]) -> io::Result<Replay> {
+        let mut reader = ByteReader::new(data);
+        if reader.take(4)? != MAGIC {
+            return Err(invalid("no es un archivo de repetición"));
+        }
+        let version = reader.take(1)?[0];
+        if version != 1 && version != VERSION {
+            return Err(invalid("versión de repetición no soportada"));
+        }
+        let seed = u64::from_le_bytes(reader.array()?);
+        let level_len = u16::from_le_bytes(reader.array()?) as usize;
+        let level = String::from_utf8(reader.take(level_len)?.to_vec())
+            .map_err(|_| invalid("nombre de nivel inválido"))?;
+        let sensitivity = if version >= 2 { f32::from_le_bytes(reader.array()?) } else { 1.0 };
+
+        let mut frames = Vec::new();
+        let run_count = u32::from_le_bytes(reader.array()?);
+        for _ in 0..run_count {
+            let count = u16::from_le_bytes(reader.array()?);
+            let keys = u16::from_le_bytes(reader.array()?);
+            let mouse_x = f32::from_le_bytes(reader.array()?);
+            let frame = FrameInput { keys, mouse_x: if mouse_x.is_nan() { None } else { Some(mouse_x) } };
+            frames.extend(std::iter::repeat_n(frame, count as usize));
+        }
+
+        Ok(Replay { seed, level, sensitivity, frames })
+    }
+}
+
+pub fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+// Lee valores en orden desde un archivo binario
+pub struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        ByteReader { data, pos: 0 }
+    }
+
+    pub fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.pos + len > self.data.len() {
+            return Err(invalid("archivo truncado"));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    fn walk_forward(ticks: usize) -> Replay {
+        let mut replay = Replay::new(1234, "maze1.txt");
+        for i in 0..ticks {
+            let keys = if i % 40 < 30 { 1 } else { 1 << 3 };
+            replay.push(FrameInput { keys, mouse_x: None });
+        }
+        replay
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let mut replay = walk_forward(200);
+        replay.push(FrameInput { keys: 0, mouse_x: Some(12.5) });
+        replay.sensitivity = 1.5;
+        let decoded = Replay::decode(&replay.encode()).unwrap();
+        assert_eq!(decoded.seed, 1234);
+        assert_eq!(decoded.sensitivity, 1.5);
+        assert_eq!(decoded.level, "maze1.txt");
+        assert_eq!(decoded.frames, replay.frames);
+    }
+
+    #[test]
+    fn test_decode_rejects_garbage() {
+        assert!(Replay::decode(b"RCRP").is_err());
+        assert!(Replay::decode(b"hola mundo").is_err());
+    }
+
+    #[test]
+    fn test_decode_version_1() {
+        let replay = walk_forward(100);
+        let mut data = replay.encode();
+        // La versión 1 no tenía la sensibilidad después del nombre del nivel
+        data[4] = 1;
+        let sensitivity_at = 4 + 1 + 8 + 2 + replay.level.len();
+        data.drain(sensitivity_at..sensitivity_at + 4);
+        let decoded = Replay::decode(&data).unwrap();
+        assert_eq!(decoded.sensitivity, 1.0);
+        assert_eq!(decoded.frames, replay.frames);
+    }
+
+    #[test]
+    fn test_replay_is_deterministic() {
+        let replay = walk_forward(600);
+        let first = Game::run_replay(&replay);
+        let second = Game::run_replay(&replay);
+        assert_eq!(first.tick, 600);
+        assert_eq!(first.player.pos, second.player.pos);
+        assert_eq!(first.player.a, second.player.a);
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Generador pseudoaleatorio xorshift64*. Es pequeño y determinista: con la misma
+// semilla produce la misma secuencia, lo que permite repetir partidas grabadas.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // El estado nunca puede ser cero
+        Rng { state: (seed ^ 0x9E37_79B9_7F4A_7C15) | 1 }
+    }
+
+    // Continúa una secuencia desde un estado guardado con state()
+    pub fn from_state(state: u64) -> Self {
+        Rng { state: state.max(1) }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    // Semilla tomada del reloj para partidas normales
+    pub fn seed_from_time() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // Número en el rango [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // Número en el rango [min, max)
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn test_range() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let v = rng.range(2.0, 5.0);
+            assert!((2.0..5.0).contains(&v));
+        }
+    }
+}
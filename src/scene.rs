@@ -0,0 +1,215 @@
+use std::time::Duration;
+use crate::audio::AudioBackend;
+use crate::campaign::{Campaign, Progress};
+use crate::framebuffer::Framebuffer;
+use crate::input::InputState;
+use crate::music::MusicController;
+use crate::score::Leaderboard;
+use crate::settings::Settings;
+
+// Lo que comparten todas las pantallas del juego
+pub struct App {
+    pub audio: Box<dyn AudioBackend>,
+    pub music: MusicController,
+    pub menu_music: &'static str,
+    pub settings: Settings,
+    pub campaign: Campaign,
+    pub progress: Progress,
+    pub leaderboard: Leaderboard,
+    pub block_size: usize,
+    pub record_path: Option<String>,
+    pub seed: Option<u64>,
+    pub fps: u32,
+    pub screen_size: (usize, usize), // Tamaño del framebuffer
+    pub window_size: (usize, usize), // Tamaño actual de la ventana, que puede cambiar
+}
+
+impl App {
+    // Posición del mouse en píxeles del framebuffer. La ventana muestra la
+    // imagen estirada sin deformarla, con franjas negras a los costados o
+    // arriba y abajo; sobre las franjas no hay posición.
+    pub fn mouse_position(&self, input: &InputState) -> Option<(usize, usize)> {
+        let (x, y) = input.mouse_pos?;
+        let (screen_width, screen_height) = (self.screen_size.0 as f32, self.screen_size.1 as f32);
+        let scale = (self.window_size.0 as f32 / screen_width).min(self.window_size.1 as f32 / screen_height);
+        if scale <= 0.0 {
+            return None;
+        }
+        let x = (x - (self.window_size.0 as f32 - screen_width * scale) / 2.0) / scale;
+        let y = (y - (self.window_size.1 as f32 - screen_height * scale) / 2.0) / scale;
+        let inside = x >= 0.0 && y >= 0.0 && x < screen_width && y < screen_height;
+        inside.then_some((x as usize, y as usize))
+    }
+}
+
+// Qué hacer con la pila de pantallas después de actualizar la de arriba
+pub enum Transition {
+    None,
+    Push(Box<dyn Scene>),    // Abre una pantalla encima, por ejemplo la pausa
+    Pop,                     // Cierra la pantalla de arriba
+    Replace(Box<dyn Scene>), // Cambia la pantalla de arriba por otra
+    Reset(Box<dyn Scene>),   // Cierra todas y deja solo la nueva
+    Quit,
+}
+
+// Una pantalla del juego: menú, partida, pausa, etc.
+pub trait Scene {
+    // Avanza un cuadro. Solo se llama en la pantalla de arriba.
+    fn update(&mut self, app: &mut App, input: &InputState, dt: Duration) -> Transition;
+
+    fn render(&self, app: &App, framebuffer: &mut Framebuffer);
+
+    // Una pantalla superpuesta deja ver la de abajo, como la pausa sobre la partida
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+// Pila de pantallas. Solo la de arriba recibe la entrada; las de abajo quedan
+// congeladas hasta que vuelvan a estar arriba.
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+}
+
+impl SceneStack {
+    pub fn new(first: Box<dyn Scene>) -> Self {
+        SceneStack { scenes: vec![first] }
+    }
+
+    // Sin pantallas el juego termina
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    pub fn update(&mut self, app: &mut App, input: &InputState, dt: Duration) {
+        let Some(top) = self.scenes.last_mut() else {
+            return;
+        };
+        match top.update(app, input, dt) {
+            Transition::None => {}
+            Transition::Push(scene) => self.scenes.push(scene),
+            Transition::Pop => {
+                self.scenes.pop();
+            }
+            Transition::Replace(scene) => {
+                self.scenes.pop();
+                self.scenes.push(scene);
+            }
+            Transition::Reset(scene) => {
+                self.scenes.clear();
+                self.scenes.push(scene);
+            }
+            Transition::Quit => self.scenes.clear(),
+        }
+    }
+
+    // Dibuja desde la última pantalla que no es superpuesta hacia arriba
+    pub fn render(&self, app: &App, framebuffer: &mut Framebuffer) {
+        framebuffer.begin_frame();
+        let first = self.scenes.iter().rposition(|scene| !scene.is_overlay()).unwrap_or(0);
+        for scene in &self.scenes[first..] {
+            scene.render(app, framebuffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::NullBackend;
+    use minifb::Key;
+
+    // Pantalla de prueba: pinta su color en un píxel y reacciona a unas teclas
+    struct Marker {
+        pixel: usize,
+        overlay: bool,
+    }
+
+    impl Scene for Marker {
+        fn update(&mut self, _app: &mut App, input: &InputState, _dt: Duration) -> Transition {
+            if input.is_pressed(Key::O) {
+                Transition::Push(Box::new(Marker { pixel: self.pixel + 1, overlay: true }))
+            } else if input.is_pressed(Key::R) {
+                Transition::Replace(Box::new(Marker { pixel: self.pixel + 1, overlay: false }))
+            } else if input.is_pressed(Key::Escape) {
+                Transition::Pop
+            } else {
+                Transition::None
+            }
+        }
+
+        fn render(&self, _app: &App, framebuffer: &mut Framebuffer) {
+            framebuffer.buffer[self.pixel] = 1;
+        }
+
+        fn is_overlay(&self) -> bool {
+            self.overlay
+        }
+    }
+
+    fn app() -> App {
+        App {
+            audio: Box::new(NullBackend),
+            music: MusicController::new(),
+            menu_music: "music.mp3",
+            settings: Settings::default(),
+            campaign: Campaign::parse("", &[]),
+            progress: Progress::new(),
+            leaderboard: Leaderboard::parse(""),
+            block_size: 50,
+            record_path: None,
+            seed: None,
+            fps: 0,
+            screen_size: (4, 1),
+            window_size: (4, 1),
+        }
+    }
+
+    fn press(stack: &mut SceneStack, app: &mut App, key: Key) {
+        let mut input = InputState::new();
+        input.update_with(vec![key], None);
+        stack.update(app, &input, Duration::ZERO);
+    }
+
+    fn rendered(stack: &SceneStack, app: &App) -> Vec<u32> {
+        let mut framebuffer = Framebuffer::new(4, 1);
+        stack.render(app, &mut framebuffer);
+        framebuffer.buffer
+    }
+
+    #[test]
+    fn test_overlay_draws_over_scene_below() {
+        let mut app = app();
+        let mut stack = SceneStack::new(Box::new(Marker { pixel: 0, overlay: false }));
+        press(&mut stack, &mut app, Key::O);
+        assert_eq!(rendered(&stack, &app), vec![1, 1, 0, 0]);
+
+        // Una pantalla opaca tapa todo lo de abajo
+        press(&mut stack, &mut app, Key::R);
+        assert_eq!(rendered(&stack, &app), vec![0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn test_mouse_position_skips_letterbox() {
+        let mut app = app();
+        app.screen_size = (400, 200);
+        // El doble de alto del que hace falta: franjas de 200 arriba y abajo
+        app.window_size = (800, 800);
+        let mut input = InputState::new();
+        input.update_with(vec![], Some((400.0, 400.0)));
+        assert_eq!(app.mouse_position(&input), Some((200, 100)));
+        input.update_with(vec![], Some((400.0, 100.0)));
+        assert_eq!(app.mouse_position(&input), None);
+    }
+
+    #[test]
+    fn test_popping_last_scene_empties_stack() {
+        let mut app = app();
+        let mut stack = SceneStack::new(Box::new(Marker { pixel: 0, overlay: false }));
+        press(&mut stack, &mut app, Key::O);
+        press(&mut stack, &mut app, Key::Escape);
+        assert!(!stack.is_empty());
+        press(&mut stack, &mut app, Key::Escape);
+        assert!(stack.is_empty());
+    }
+}
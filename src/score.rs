@@ -0,0 +1,162 @@
+use std::fs;
+use std::io;
+use crate::game::{Game, TICK_RATE};
+
+// Archivo con los mejores puntajes de todos los niveles
+pub const RECORDS_FILE: &str = "records.txt";
+
+// Cuántos puntajes se guardan por tabla
+const TABLE_SIZE: usize = 5;
+
+// Puntaje de un nivel, separado en sus partes para mostrarlo
+#[derive(PartialEq, Debug)]
+pub struct Score {
+    pub time: i64,         // Bono por terminar rápido; baja 10 puntos por segundo
+    pub steps: i64,        // Cada paso resta 2 puntos
+    pub scares: i64,       // Cada susto aguantado suma 250
+    pub collectibles: i64, // Cada objeto suma 500
+    pub total: u32,
+}
+
+impl Score {
+    pub fn of(game: &Game) -> Score {
+        let seconds = (game.tick / TICK_RATE as u64) as i64;
+        let collected = game.collected.iter().filter(|&&collected| collected).count() as i64;
+        let time = (5000 - seconds * 10).max(0);
+        let steps = -2 * game.steps as i64;
+        let scares = 250 * game.scares as i64;
+        let collectibles = 500 * collected;
+        let total = (time + steps + scares + collectibles).max(0) as u32;
+        Score { time, steps, scares, collectibles, total }
+    }
+}
+
+// Un puntaje de la tabla
+#[derive(Clone, PartialEq, Debug)]
+pub struct Record {
+    pub initials: String,
+    pub score: u32,
+    pub ticks: u64,
+}
+
+// Tablas de puntajes locales. Cada nivel tiene la suya; las partidas con
+// semilla fija tienen una tabla aparte por semilla, porque los sustos llegan
+// siempre en el mismo momento. El archivo tiene líneas como:
+//
+//   maze1.txt;ABC;4210;3600
+//   maze1.txt@1234;XYZ;3900;4100
+pub struct Leaderboard {
+    entries: Vec<(String, Record)>,
+}
+
+impl Leaderboard {
+    pub fn load(path: &str) -> Leaderboard {
+        let text = fs::read_to_string(path).unwrap_or_default();
+        Leaderboard::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Leaderboard {
+        let mut entries = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let parts: Vec<&str> = line.trim().split(';').collect();
+            let [table, initials, score, ticks] = parts.as_slice() else {
+                if !line.trim().is_empty() {
+                    eprintln!("Línea {} ignorada en los puntajes: {}", number + 1, line);
+                }
+                continue;
+            };
+            match (score.parse(), ticks.parse()) {
+                (Ok(score), Ok(ticks)) => {
+                    let record = Record { initials: initials.to_string(), score, ticks };
+                    entries.push((table.to_string(), record));
+                }
+                _ => eprintln!("Línea {} ignorada en los puntajes: {}", number + 1, line),
+            }
+        }
+        Leaderboard { entries }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.encode())
+    }
+
+    pub fn encode(&self) -> String {
+        self.entries
+            .iter()
+            .map(|(table, record)| format!("{};{};{};{}\n", table, record.initials, record.score, record.ticks))
+            .collect()
+    }
+
+    // Nombre de la tabla de un nivel, con la semilla si es una partida con semilla fija
+    pub fn table_name(level: &str, seed: Option<u64>) -> String {
+        match seed {
+            Some(seed) => format!("{}@{}", level, seed),
+            None => level.to_string(),
+        }
+    }
+
+    // Mejores puntajes de una tabla, de mayor a menor; a igual puntaje gana el más rápido
+    pub fn top(&self, table: &str) -> Vec<&Record> {
+        let mut records: Vec<&Record> = self.entries
+            .iter()
+            .filter(|(name, _)| name == table)
+            .map(|(_, record)| record)
+            .collect();
+        records.sort_by(|a, b| b.score.cmp(&a.score).then(a.ticks.cmp(&b.ticks)));
+        records.truncate(TABLE_SIZE);
+        records
+    }
+
+    // Un puntaje entra si la tabla no está llena o si supera al último
+    pub fn qualifies(&self, table: &str, score: u32) -> bool {
+        let top = self.top(table);
+        top.len() < TABLE_SIZE || top.last().is_some_and(|last| score > last.score)
+    }
+
+    // Agrega un puntaje y descarta los que quedaron fuera de la tabla
+    pub fn add(&mut self, table: &str, record: Record) {
+        self.entries.push((table.to_string(), record));
+        let kept: Vec<Record> = self.top(table).into_iter().cloned().collect();
+        self.entries.retain(|(name, _)| name != table);
+        self.entries.extend(kept.into_iter().map(|record| (table.to_string(), record)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(initials: &str, score: u32, ticks: u64) -> Record {
+        Record { initials: initials.to_string(), score, ticks }
+    }
+
+    #[test]
+    fn test_tables_keep_best_scores() {
+        let mut board = Leaderboard::parse("maze1.txt;AAA;100;10\nmaze1.txt@7;BBB;900;10\nbasura\n");
+        for score in [300, 200, 500, 400, 50] {
+            board.add("maze1.txt", record("CCC", score, 20));
+        }
+        let scores: Vec<u32> = board.top("maze1.txt").iter().map(|record| record.score).collect();
+        assert_eq!(scores, [500, 400, 300, 200, 100]);
+        assert!(!board.qualifies("maze1.txt", 100));
+        assert!(board.qualifies("maze1.txt", 101));
+
+        // La tabla con semilla es independiente
+        let board = Leaderboard::parse(&board.encode());
+        assert_eq!(board.top(&Leaderboard::table_name("maze1.txt", Some(7)))[0].initials, "BBB");
+    }
+
+    #[test]
+    fn test_score_rewards_collectibles_and_speed() {
+        let mut game = Game::new("maze3.txt", 1);
+        let base = Score::of(&game);
+        assert_eq!(base.total, 5000);
+
+        game.collected[0] = true;
+        game.steps = 100;
+        game.tick = 30 * TICK_RATE as u64;
+        let score = Score::of(&game);
+        assert_eq!(score.time, 4700);
+        assert_eq!(score.total, 4700 - 200 + 500);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::audio::{AudioBackend, Channel};
+use crate::textures::Filter;
+
+// Opciones que el jugador puede cambiar desde el menú
+pub struct Settings {
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub muted: bool,
+    pub sensitivity: f32,       // Multiplica el giro con el mouse
+    pub fov: f32,               // Campo de visión en grados
+    pub fog: f32,               // 0.0 sin niebla, 1.0 niebla densa
+    pub resolution_scale: f32,  // Fracción de columnas que se calculan en 3D
+    pub post_effects: bool,     // Efectos del nivel y temblor de la vista
+    pub texture_filter: Filter, // Calidad de las paredes; Nearest es lo más rápido
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            music_volume: 0.8,
+            sfx_volume: 1.0,
+            muted: false,
+            sensitivity: 1.0,
+            fov: 60.0,
+            fog: 0.0,
+            resolution_scale: 1.0,
+            post_effects: true,
+            texture_filter: Filter::Mipmap,
+        }
+    }
+}
+
+impl Settings {
+    // Cantidad de opciones que muestra el menú
+    pub const ITEMS: usize = 9;
+
+    // Aplica los volúmenes a todos los canales de audio
+    pub fn apply(&self, audio: &mut dyn AudioBackend) {
+        audio.set_channel_volume(Channel::Music, self.music_volume);
+        for channel in [Channel::Sfx, Channel::Ambience, Channel::Ui] {
+            audio.set_channel_volume(channel, self.sfx_volume);
+        }
+        audio.set_muted(self.muted);
+    }
+
+    // Texto de una opción con su valor actual
+    pub fn describe(&self, item: usize) -> String {
+        match item {
+            0 => format!("Volumen de la musica: {:.0}%", self.music_volume * 100.0),
+            1 => format!("Volumen de efectos: {:.0}%", self.sfx_volume * 100.0),
+            2 => format!("Silencio: {}", if self.muted { "si" } else { "no" }),
+            3 => format!("Sensibilidad del mouse: {:.1}", self.sensitivity),
+            4 => format!("Campo de vision: {:.0}", self.fov),
+            5 => format!("Niebla: {:.0}%", self.fog * 100.0),
+            6 => format!("Resolucion: {:.0}%", self.resolution_scale * 100.0),
+            7 => format!("Efectos de pantalla: {}", if self.post_effects { "si" } else { "no" }),
+            _ => {
+                let quality = match self.texture_filter {
+                    Filter::Nearest => "baja",
+                    Filter::Mipmap => "media",
+                    Filter::Bilinear => "alta",
+                };
+                format!("Calidad de texturas: {}", quality)
+            }
+        }
+    }
+
+    // Sube o baja una opción un paso. Con `wrap`, al pasar del máximo vuelve al
+    // mínimo, para poder recorrer los valores solo con clics.
+    pub fn adjust(&mut self, item: usize, up: bool, wrap: bool) {
+        let (value, step, min, max) = match item {
+            0 => (&mut self.music_volume, 0.1, 0.0, 1.0),
+            1 => (&mut self.sfx_volume, 0.1, 0.0, 1.0),
+            2 => {
+                self.muted = !self.muted;
+                return;
+            }
+            3 => (&mut self.sensitivity, 0.1, 0.5, 2.0),
+            4 => (&mut self.fov, 5.0, 50.0, 100.0),
+            5 => (&mut self.fog, 0.1, 0.0, 1.0),
+            6 => (&mut self.resolution_scale, 0.25, 0.25, 1.0),
+            7 => {
+                self.post_effects = !self.post_effects;
+                return;
+            }
+            _ => {
+                let index = Filter::ALL.iter().position(|&filter| filter == self.texture_filter).unwrap();
+                let step = if up { 1 } else { Filter::ALL.len() - 1 };
+                self.texture_filter = Filter::ALL[(index + step) % Filter::ALL.len()];
+                return;
+            }
+        };
+        let next = if up { *value + step } else { *value - step };
+        // Redondear evita que los pasos de 0.1 acumulen error
+        let next = (next / step).round() * step;
+        *value = if wrap && next > max + step / 2.0 {
+            min
+        } else {
+            next.clamp(min, max)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjust_clamps_and_wraps() {
+        let mut settings = Settings::default();
+        for _ in 0..20 {
+            settings.adjust(4, true, false);
+        }
+        assert_eq!(settings.fov, 100.0);
+        settings.adjust(4, true, true);
+        assert_eq!(settings.fov, 50.0);
+
+        for _ in 0..3 {
+            settings.adjust(0, false, false);
+        }
+        assert!((settings.music_volume - 0.5).abs() < 1e-6);
+
+        // La calidad de texturas da la vuelta en los dos sentidos
+        assert_eq!(settings.texture_filter, Filter::Mipmap);
+        settings.adjust(8, true, false);
+        assert_eq!(settings.texture_filter, Filter::Bilinear);
+        settings.adjust(8, true, false);
+        assert_eq!(settings.texture_filter, Filter::Nearest);
+        settings.adjust(8, false, false);
+        assert_eq!(settings.texture_filter, Filter::Bilinear);
+    }
+}
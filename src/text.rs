@@ -0,0 +1,213 @@
+use rusttype::{point, PositionedGlyph, Scale};
+use crate::fonts::FontId;
+
+// Alineación respecto al punto donde se dibuja el texto
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Align {
+    Start, // Izquierda o arriba
+    Center,
+    End,   // Derecha o abajo
+}
+
+impl Align {
+    fn factor(self) -> f32 {
+        match self {
+            Align::Start => 0.0,
+            Align::Center => 0.5,
+            Align::End => 1.0,
+        }
+    }
+}
+
+// Cómo se dibuja un texto. Por defecto queda arriba a la izquierda del punto,
+// sin cortar líneas y sin sombra ni borde.
+#[derive(Clone, Copy, Debug)]
+pub struct TextStyle {
+    pub font: FontId,
+    pub scale: Scale,
+    pub color: u32,
+    pub align: Align,
+    pub valign: Align,
+    pub max_width: Option<usize>, // Corta las líneas entre palabras para no pasar de este ancho
+    pub shadow: Option<u32>,      // Color de la sombra, dos píxeles abajo a la derecha
+    pub outline: Option<u32>,     // Color del borde de un píxel alrededor de cada letra
+}
+
+impl TextStyle {
+    pub fn new(font: FontId, size: f32, color: u32) -> TextStyle {
+        TextStyle {
+            font,
+            scale: Scale::uniform(size),
+            color,
+            align: Align::Start,
+            valign: Align::Start,
+            max_width: None,
+            shadow: None,
+            outline: None,
+        }
+    }
+
+    pub fn align(mut self, align: Align, valign: Align) -> TextStyle {
+        self.align = align;
+        self.valign = valign;
+        self
+    }
+
+    pub fn wrap(mut self, max_width: usize) -> TextStyle {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn shadow(mut self, color: u32) -> TextStyle {
+        self.shadow = Some(color);
+        self
+    }
+
+    pub fn outline(mut self, color: u32) -> TextStyle {
+        self.outline = Some(color);
+        self
+    }
+}
+
+// Un trozo de texto con su propio color
+pub struct Span<'a> {
+    pub text: &'a str,
+    pub color: u32,
+}
+
+// Rectángulo que ocupa un texto en pantalla
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TextBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: usize,
+    pub height: usize,
+}
+
+// Un glifo listo para dibujar, con su color
+pub type PlacedGlyph = (PositionedGlyph<'static>, u32);
+
+// Medidas de un texto dibujado en (x, y) con el estilo dado
+pub fn measure_text(text: &str, x: i32, y: i32, style: &TextStyle) -> TextBounds {
+    layout(&[Span { text, color: style.color }], x, y, style).1
+}
+
+// Ubica cada letra de los trozos de texto: corta las líneas, aplica el
+// interletrado de la fuente y alinea cada línea respecto al punto (x, y)
+pub fn layout(spans: &[Span], x: i32, y: i32, style: &TextStyle) -> (Vec<PlacedGlyph>, TextBounds) {
+    let font = style.font.font();
+    let v_metrics = font.v_metrics(style.scale);
+    let line_height = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
+
+    // Ancho de una secuencia de letras, con el interletrado entre cada par
+    let width_of = |chars: &[(char, u32)]| {
+        let mut width = 0.0;
+        let mut previous = None;
+        for &(c, _) in chars {
+            let glyph = font.glyph(c);
+            let id = glyph.id();
+            if let Some(previous) = previous {
+                width += font.pair_kerning(style.scale, previous, id);
+            }
+            width += glyph.scaled(style.scale).h_metrics().advance_width;
+            previous = Some(id);
+        }
+        width
+    };
+
+    // Cortar en líneas: los saltos de línea siempre, y los espacios si la
+    // siguiente palabra no entra
+    let mut lines: Vec<Vec<(char, u32)>> = vec![Vec::new()];
+    let mut word: Vec<(char, u32)> = Vec::new();
+    let chars = spans.iter().flat_map(|span| span.text.chars().map(move |c| (c, span.color)));
+    for (c, color) in chars.chain([('\n', 0)]) {
+        if c != ' ' && c != '\n' {
+            word.push((c, color));
+            continue;
+        }
+        let line = lines.last_mut().unwrap();
+        if let Some(max_width) = style.max_width {
+            let mut joined = line.clone();
+            joined.extend(&word);
+            if !line.is_empty() && width_of(&joined) > max_width as f32 {
+                while line.last().is_some_and(|&(c, _)| c == ' ') {
+                    line.pop();
+                }
+                lines.push(Vec::new());
+            }
+        }
+        lines.last_mut().unwrap().append(&mut word);
+        if c == '\n' {
+            lines.push(Vec::new());
+        } else {
+            lines.last_mut().unwrap().push((c, color));
+        }
+    }
+    // El salto agregado al final deja una línea vacía de más
+    lines.pop();
+
+    let widths: Vec<f32> = lines.iter().map(|line| width_of(line)).collect();
+    let block_width = widths.iter().cloned().fold(0.0, f32::max);
+    let block_height = line_height * lines.len() as f32 - v_metrics.line_gap;
+    let top = y as f32 - block_height * style.valign.factor();
+
+    let mut glyphs = Vec::new();
+    for (index, (line, width)) in lines.iter().zip(&widths).enumerate() {
+        let baseline = top + v_metrics.ascent + line_height * index as f32;
+        let mut caret = x as f32 - width * style.align.factor();
+        let mut previous = None;
+        for &(c, color) in line {
+            let glyph = font.glyph(c);
+            if let Some(previous) = previous {
+                caret += font.pair_kerning(style.scale, previous, glyph.id());
+            }
+            previous = Some(glyph.id());
+            let glyph = glyph.scaled(style.scale);
+            let advance = glyph.h_metrics().advance_width;
+            glyphs.push((glyph.positioned(point(caret, baseline)), color));
+            caret += advance;
+        }
+    }
+
+    let bounds = TextBounds {
+        x: (x as f32 - block_width * style.align.factor()).floor() as i32,
+        y: top.floor() as i32,
+        width: block_width.ceil() as usize,
+        height: block_height.max(0.0).ceil() as usize,
+    };
+    (glyphs, bounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::Framebuffer;
+
+    #[test]
+    fn test_wrap_and_align() {
+        let style = TextStyle::new(FontId::Nasa, 20.0, 0xFFFFFF);
+        let single = measure_text("uno dos tres cuatro", 0, 0, &style);
+        let wrapped = measure_text("uno dos tres cuatro", 0, 0, &style.wrap(single.width / 2));
+        assert!(wrapped.width <= single.width / 2);
+        assert!(wrapped.height >= single.height * 2);
+
+        // Centrado en los dos ejes, el punto queda en el medio del texto
+        let centered = measure_text("hola", 100, 50, &style.align(Align::Center, Align::Center));
+        assert!((centered.x + centered.width as i32 / 2 - 100).abs() <= 1);
+        assert!((centered.y + centered.height as i32 / 2 - 50).abs() <= 1);
+        let right = measure_text("hola", 100, 50, &style.align(Align::End, Align::Start));
+        assert!((right.x + right.width as i32 - 100).abs() <= 1);
+    }
+
+    #[test]
+    fn test_edges_are_blended_and_spans_keep_colors() {
+        let mut framebuffer = Framebuffer::new(200, 60);
+        let style = TextStyle::new(FontId::Nasa, 30.0, 0xFFFFFF);
+        framebuffer.draw_spans(&[Span { text: "O", color: 0xFF0000 }, Span { text: "O", color: 0x0000FF }], 0, 0, &style);
+        // Los bordes tienen tonos intermedios entre el fondo y la letra
+        assert!(framebuffer.buffer.iter().any(|&pixel| pixel > 0 && pixel < 0xFF0000 && pixel & 0xFFFF == 0));
+        assert!(framebuffer.buffer.contains(&0xFF0000));
+        assert!(framebuffer.buffer.contains(&0x0000FF));
+        assert!(!framebuffer.buffer.contains(&0xFFFFFF));
+    }
+}
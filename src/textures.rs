@@ -1,42 +1,315 @@
 extern crate image;
 
-use image::{ImageReader, Pixel};
-use image::{DynamicImage, GenericImageView};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageError, ImageReader, Rgba, RgbaImage};
 
+// Por qué no se pudo cargar una textura
+#[derive(Debug)]
+pub enum TextureError {
+    NotFound(String),
+    Io(String, io::Error),
+    Decode(String, ImageError),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::NotFound(file) => write!(f, "no existe la textura {}", file),
+            TextureError::Io(file, e) => write!(f, "no se pudo leer la textura {}: {}", file, e),
+            TextureError::Decode(file, e) => write!(f, "{} no es una imagen válida: {}", file, e),
+        }
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextureError::NotFound(_) => None,
+            TextureError::Io(_, e) => Some(e),
+            TextureError::Decode(_, e) => Some(e),
+        }
+    }
+}
+
+// Qué hacer con las coordenadas que caen fuera de la textura
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AddressMode {
+    Wrap,  // La textura se repite, como en las paredes
+    Clamp, // Se usa el borde más cercano
+}
+
+// Cómo se eligen los colores de una textura que se ve más chica o más grande
+// que su tamaño real
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Filter {
+    Nearest,  // El píxel más cercano de la textura original: lo más rápido
+    Mipmap,   // El píxel más cercano de la versión reducida que corresponde
+    Bilinear, // Como Mipmap, pero mezclando los cuatro píxeles vecinos
+}
+
+impl Filter {
+    pub const ALL: [Filter; 3] = [Filter::Nearest, Filter::Mipmap, Filter::Bilinear];
+}
+
+// Una versión de la textura. Los píxeles se guardan por columnas, una detrás
+// de otra, porque las paredes se dibujan de a tiras verticales.
+struct MipLevel {
+    width: u32,
+    height: u32,
+    pixels: Vec<u32>, // 0xAARRGGBB, el píxel (x, y) en x * height + y
+}
+
+impl MipLevel {
+    fn get(&self, x: i32, y: i32, mode: AddressMode) -> u32 {
+        let x = address(x, self.width, mode);
+        let y = address(y, self.height, mode);
+        self.pixels[x * self.height as usize + y]
+    }
+
+    // Mitad de ancho y de alto, promediando cada cuadro de 2x2
+    fn half(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for x in 0..width as i32 {
+            for y in 0..height as i32 {
+                let corners = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| self.get(2 * x + dx, 2 * y + dy, AddressMode::Clamp));
+                pixels.push(average(&corners));
+            }
+        }
+        MipLevel { width, height, pixels }
+    }
+}
+
+// La textura y sus versiones reducidas a la mitad, hasta llegar a 1x1
 pub struct Texture {
-    image: DynamicImage,
     pub width: u32,
     pub height: u32,
-    pub color_array: Vec<Vec<u32>>,
+    levels: Vec<MipLevel>,
 }
 
 impl Texture {
-    pub fn new(file_path: &str) -> Texture {
-        let img = ImageReader::open(file_path).unwrap().decode().unwrap();
-        let width = img.width();
-        let height = img.height();
-        let mut t = Texture { image: img, width, height, color_array: vec![vec![0; height as usize]; width as usize] };
-        t.load_array();
-        t
-    }
-
-    fn load_array(&mut self) {
-        for x in 0..self.width {
-            for y in 0..self.height {
-                let pixel = self.image.get_pixel(x, y).to_rgb();
-                let r = pixel[0];
-                let g = pixel[1];
-                let b = pixel[2];
-                let color = ((r as u32) << 16) | ((g as u32) << 8) | (b as u32);
-                self.color_array[x as usize][y as usize] = color;
+    // Las imágenes más grandes que `max_size` de lado se achican al cargarlas,
+    // sin cambiar la proporción
+    pub fn load(file_path: &str, max_size: u32) -> Result<Texture, TextureError> {
+        let reader = ImageReader::open(file_path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => TextureError::NotFound(file_path.to_string()),
+            _ => TextureError::Io(file_path.to_string(), e),
+        })?;
+        let img = reader.decode().map_err(|e| TextureError::Decode(file_path.to_string(), e))?;
+        Ok(Texture::from_image(&img, max_size))
+    }
+
+    // Si la textura no se puede cargar se avisa y se usa el reemplazo, para
+    // que el juego siga andando
+    pub fn load_or_placeholder(file_path: &str, max_size: u32) -> Texture {
+        Texture::load(file_path, max_size).unwrap_or_else(|e| {
+            eprintln!("{}; se usa una textura de reemplazo", e);
+            Texture::placeholder()
+        })
+    }
+
+    // Cuadriculado magenta y negro, que se nota enseguida en pantalla
+    pub fn placeholder() -> Texture {
+        let image = RgbaImage::from_fn(64, 64, |x, y| {
+            if (x / 8 + y / 8) % 2 == 0 { Rgba([255, 0, 255, 255]) } else { Rgba([0, 0, 0, 255]) }
+        });
+        Texture::from_image(&DynamicImage::ImageRgba8(image), 64)
+    }
+
+    fn from_image(img: &DynamicImage, max_size: u32) -> Texture {
+        let image = if img.width() > max_size || img.height() > max_size {
+            img.resize(max_size, max_size, FilterType::Triangle).to_rgba8()
+        } else {
+            img.to_rgba8()
+        };
+        let (width, height) = image.dimensions();
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for x in 0..width {
+            for y in 0..height {
+                let [r, g, b, a] = image.get_pixel(x, y).0;
+                pixels.push(((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32));
             }
         }
+
+        Texture::from_pixels(width, height, pixels)
     }
 
-    pub fn get_pixel_color(&self, x: u32, y: u32) -> u32 {
-        if x >= self.width {
-            return 0xFF0000;
+    fn from_pixels(width: u32, height: u32, pixels: Vec<u32>) -> Texture {
+        let mut levels = vec![MipLevel { width, height, pixels }];
+        while let Some(last) = levels.last().filter(|level| level.width > 1 || level.height > 1) {
+            let next = last.half();
+            levels.push(next);
         }
-        self.color_array[x as usize][y as usize]
+        Texture { width, height, levels }
     }
-}
\ No newline at end of file
+
+    // Copia espejada o no, con cada color multiplicado por `tint` (0xFFFFFF
+    // lo deja igual). La transparencia no cambia.
+    pub fn variant(&self, flip: bool, tint: u32) -> Texture {
+        let base = &self.levels[0];
+        let mut pixels = Vec::with_capacity(base.pixels.len());
+        for x in 0..self.width as i32 {
+            let source = if flip { self.width as i32 - 1 - x } else { x };
+            for y in 0..self.height as i32 {
+                pixels.push(multiply(base.get(source, y, AddressMode::Clamp), tint));
+            }
+        }
+        Texture::from_pixels(self.width, self.height, pixels)
+    }
+
+    // Color 0xAARRGGBB del píxel (x, y). Acepta cualquier coordenada.
+    pub fn sample(&self, x: i32, y: i32, mode: AddressMode) -> u32 {
+        self.levels[0].get(x, y, mode)
+    }
+
+    // Versión que corresponde cuando cada píxel de pantalla cubre
+    // `texels_per_pixel` píxeles de la textura original
+    pub fn mip_level(&self, texels_per_pixel: f32) -> usize {
+        let level = texels_per_pixel.max(1.0).log2().floor() as usize;
+        level.min(self.levels.len() - 1)
+    }
+
+    // La columna de una versión, de arriba a abajo. `u` va de 0.0 a 1.0 a lo
+    // ancho de la textura.
+    pub fn column(&self, level: usize, u: f32, mode: AddressMode) -> &[u32] {
+        let level = &self.levels[level];
+        let height = level.height as usize;
+        let start = address((u * level.width as f32).floor() as i32, level.width, mode) * height;
+        &level.pixels[start..start + height]
+    }
+
+    // Color en (u, v), de 0.0 a 1.0 en cada eje, mezclando los cuatro píxeles
+    // más cercanos de la versión
+    pub fn sample_bilinear(&self, level: usize, u: f32, v: f32, mode: AddressMode) -> u32 {
+        let level = &self.levels[level];
+        let x = u * level.width as f32 - 0.5;
+        let y = v * level.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+        let top = mix(level.get(x0, y0, mode), level.get(x0 + 1, y0, mode), fx);
+        let bottom = mix(level.get(x0, y0 + 1, mode), level.get(x0 + 1, y0 + 1, mode), fx);
+        mix(top, bottom, fy)
+    }
+}
+
+// Lleva una coordenada al rango 0..size. Con tamaños potencia de dos repetir
+// es solo una máscara.
+fn address(value: i32, size: u32, mode: AddressMode) -> usize {
+    match mode {
+        AddressMode::Wrap if size.is_power_of_two() => (value as u32 & (size - 1)) as usize,
+        AddressMode::Wrap => value.rem_euclid(size as i32) as usize,
+        AddressMode::Clamp => value.clamp(0, size as i32 - 1) as usize,
+    }
+}
+
+// Promedio de cada canal, alfa incluido
+fn average(colors: &[u32]) -> u32 {
+    let mut color = 0;
+    for shift in [24, 16, 8, 0] {
+        let sum: u32 = colors.iter().map(|c| (c >> shift) & 0xFF).sum();
+        color |= ((sum + colors.len() as u32 / 2) / colors.len() as u32) << shift;
+    }
+    color
+}
+
+// Multiplica cada canal de color por el del tinte, sin tocar el alfa
+fn multiply(color: u32, tint: u32) -> u32 {
+    let mut result = color & 0xFF000000;
+    for shift in [16, 8, 0] {
+        let channel = ((color >> shift) & 0xFF) * ((tint >> shift) & 0xFF) / 255;
+        result |= channel << shift;
+    }
+    result
+}
+
+// Mezcla de cada canal: t = 0.0 da `a` y t = 1.0 da `b`
+fn mix(a: u32, b: u32, t: f32) -> u32 {
+    let mut color = 0;
+    for shift in [24, 16, 8, 0] {
+        let from = ((a >> shift) & 0xFF) as f32;
+        let to = ((b >> shift) & 0xFF) as f32;
+        color |= ((from + (to - from) * t).round() as u32) << shift;
+    }
+    color
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_errors() {
+        assert!(matches!(Texture::load("no_existe.png", 64), Err(TextureError::NotFound(_))));
+        // Un archivo que existe pero no es una imagen
+        let error = Texture::load("maze1.txt", 64).err().unwrap();
+        assert!(matches!(error, TextureError::Decode(_, _)));
+        assert!(error.to_string().contains("maze1.txt"));
+    }
+
+    #[test]
+    fn test_placeholder_is_opaque_checkerboard() {
+        let texture = Texture::load_or_placeholder("no_existe.png", 64);
+        assert_eq!((texture.width, texture.height), (64, 64));
+        assert_eq!(texture.sample(0, 0, AddressMode::Clamp), 0xFFFF00FF);
+        assert_eq!(texture.sample(8, 0, AddressMode::Clamp), 0xFF000000);
+    }
+
+    #[test]
+    fn test_address_modes() {
+        let texture = Texture::placeholder();
+        assert_eq!(texture.sample(64, 0, AddressMode::Wrap), texture.sample(0, 0, AddressMode::Wrap));
+        assert_eq!(texture.sample(-1, 0, AddressMode::Wrap), 0xFF000000);
+        assert_eq!(texture.sample(-5, 200, AddressMode::Clamp), texture.sample(0, 63, AddressMode::Clamp));
+        assert_eq!(texture.column(0, 65.5 / 64.0, AddressMode::Wrap)[8], texture.sample(1, 8, AddressMode::Clamp));
+        // Sin potencia de dos también se repite
+        assert_eq!(address(-1, 10, AddressMode::Wrap), 9);
+        assert_eq!(address(23, 10, AddressMode::Wrap), 3);
+    }
+
+    #[test]
+    fn test_large_images_are_reduced() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(400, 200, Rgba([10, 20, 30, 255])));
+        let texture = Texture::from_image(&image, 100);
+        assert_eq!((texture.width, texture.height), (100, 50));
+        assert_eq!(texture.column(0, 0.0, AddressMode::Clamp).len(), 50);
+        assert_eq!(texture.sample(99, 49, AddressMode::Clamp), 0xFF0A141E);
+        let small = Texture::from_image(&image, 1024);
+        assert_eq!((small.width, small.height), (400, 200));
+    }
+
+    #[test]
+    fn test_mip_chain_and_bilinear() {
+        let texture = Texture::placeholder();
+        // 64, 32, 16, 8, 4, 2, 1
+        assert_eq!(texture.levels.len(), 7);
+        assert_eq!(texture.mip_level(0.5), 0);
+        assert_eq!(texture.mip_level(4.5), 2);
+        assert_eq!(texture.mip_level(1000.0), 6);
+        // Los cuadros de 8 píxeles siguen enteros hasta la versión de 8x8,
+        // y en la de 1x1 queda el promedio de magenta y negro
+        assert_eq!(texture.column(3, 0.0, AddressMode::Clamp)[0], 0xFFFF00FF);
+        assert_eq!(texture.column(6, 0.0, AddressMode::Clamp), &[0xFF800080]);
+
+        // Justo en el medio de dos píxeles se mezclan por partes iguales
+        assert_eq!(texture.sample_bilinear(3, 0.125, 0.0625, AddressMode::Wrap), 0xFF800080);
+        assert_eq!(texture.sample_bilinear(3, 0.0625, 0.0625, AddressMode::Wrap), 0xFFFF00FF);
+    }
+
+    #[test]
+    fn test_flipped_and_tinted_variant() {
+        let texture = Texture::placeholder();
+        let same = texture.variant(false, 0xFFFFFF);
+        assert_eq!(same.sample(3, 5, AddressMode::Clamp), texture.sample(3, 5, AddressMode::Clamp));
+
+        // Espejada, el primer cuadro pasa a la otra punta y el magenta queda en rojo
+        let red = texture.variant(true, 0xFF0000);
+        assert_eq!(red.sample(63, 0, AddressMode::Clamp), 0xFFFF0000);
+        assert_eq!(red.sample(55, 0, AddressMode::Clamp), 0xFF000000);
+        assert_eq!(red.levels.len(), texture.levels.len());
+    }
+}